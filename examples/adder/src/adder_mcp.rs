@@ -1,7 +1,6 @@
-use ic_rmcp::{model::*, schema_for_type, Context, Error, Handler};
+use ic_rmcp::{model::*, tool, tool_handler, tool_router, Context, Error, Handler};
 use schemars::JsonSchema;
 use serde::Deserialize;
-use serde_json::{from_value, Value};
 
 #[derive(JsonSchema, Deserialize)]
 struct AddRequest {
@@ -11,6 +10,18 @@ struct AddRequest {
 
 pub struct Adder;
 
+#[tool_router]
+impl Adder {
+    /// Add two numbers
+    #[tool]
+    async fn add(&self, args: AddRequest) -> Result<CallToolResult, Error> {
+        Ok(CallToolResult::success(
+            Content::text(format!("{:.2}", args.a + args.b)).into_contents(),
+        ))
+    }
+}
+
+#[tool_handler]
 impl Handler for Adder {
     fn get_info(&self, _: Context) -> ServerInfo {
         ServerInfo {
@@ -18,49 +29,12 @@ impl Handler for Adder {
             server_info: Implementation {
                 name: "Adder".to_string(),
                 version: "1.0.0".to_string(),
+                title: None,
+                icons: None,
+                website_url: None,
             },
             instructions: Some("This server is a tool help adding two number.".to_string()),
             ..Default::default()
         }
     }
-
-    async fn list_tools(
-        &self,
-        _: Context,
-        _: Option<PaginatedRequestParam>,
-    ) -> Result<ListToolsResult, Error> {
-        ic_cdk::println!("List tools called");
-        Ok(ListToolsResult {
-            next_cursor: None,
-            tools: vec![Tool::new(
-                "add",
-                "Add two numbers",
-                schema_for_type::<AddRequest>(),
-            )],
-        })
-    }
-
-    async fn call_tool(
-        &self,
-        _: Context,
-        requests: CallToolRequestParam,
-    ) -> Result<CallToolResult, Error> {
-        ic_cdk::println!(
-            "Call tool: {}, arguments: {:?}",
-            requests.name,
-            requests.arguments
-        );
-        match requests.name.as_ref() {
-            "add" => match requests.arguments {
-                None => Err(Error::invalid_params("invalid arguments to tool add", None)),
-                Some(data) => match from_value::<AddRequest>(Value::Object(data)) {
-                    Err(_) => Err(Error::invalid_params("invalid arguments to tool add", None)),
-                    Ok(args) => Ok(CallToolResult::success(
-                        Content::text(format!("{:.2}", args.a + args.b)).into_contents(),
-                    )),
-                },
-            },
-            _ => Err(Error::invalid_params("not found tool", None)),
-        }
-    }
 }
@@ -1,9 +1,10 @@
 use candid::CandidType;
 use chrono::DateTime;
 use ic_cdk::{api::time, init, query, update};
-use ic_http_certification::{HttpRequest, HttpResponse, StatusCode};
+use ic_http_certification::{HttpRequest, HttpResponse};
 use ic_rmcp::{
-    model::*, schema_for_type, Context, Error, Handler, IssuerConfig, OAuthConfig, Server,
+    model::*, schema_for_type, tool_not_found, Context, Error, Handler, IssuerConfig, OAuthConfig,
+    Server, ToolExt,
 };
 use serde::Deserialize;
 use std::cell::RefCell;
@@ -27,12 +28,23 @@ struct InitArgs {
     audience: String,
 }
 
+fn oauth_config() -> OAuthConfig {
+    ARGS.with_borrow(|args| OAuthConfig {
+        metadata_url: args.metadata_url.clone(),
+        resource: args.resource.clone(),
+        issuer_configs: IssuerConfig {
+            issuer: args.issuer.clone(),
+            jwks_url: args.jwks_url.clone(),
+            authorization_server: args.authorization_server.clone(),
+            audience: args.audience.clone(),
+        },
+        scopes_supported: vec![],
+    })
+}
+
 #[query]
-fn http_request(_: HttpRequest) -> HttpResponse {
-    HttpResponse::builder()
-        .with_status_code(StatusCode::OK)
-        .with_upgrade(true)
-        .build()
+async fn http_request(req: HttpRequest<'_>) -> HttpResponse<'_> {
+    Clock {}.handle_query_with_oauth(&req, oauth_config()).await
 }
 
 struct Clock;
@@ -44,6 +56,9 @@ impl Handler for Clock {
             server_info: Implementation {
                 name: "Clock".to_string(),
                 version: "1.0.0".to_string(),
+                title: None,
+                icons: None,
+                website_url: None,
             },
             instructions: Some(
                 "This server provides a tell_time tool that tell the current time in GMT+0"
@@ -64,7 +79,8 @@ impl Handler for Clock {
                 "tell_time",
                 "Tell the current time in GMT+0",
                 schema_for_type::<EmptyObject>(),
-            )],
+            )
+            .read_only(true)],
         })
     }
 
@@ -82,29 +98,14 @@ impl Handler for Clock {
                 ))
                 .into_contents(),
             )),
-            _ => Err(Error::invalid_params("not found tool", None)),
+            _ => Err(tool_not_found(&requests.name)),
         }
     }
 }
 
 #[update]
 async fn http_request_update(req: HttpRequest<'_>) -> HttpResponse<'_> {
-    Clock {}
-        .handle_with_oauth(
-            &req,
-            ARGS.with_borrow(|args| OAuthConfig {
-                metadata_url: args.metadata_url.clone(),
-                resource: args.resource.clone(),
-                issuer_configs: IssuerConfig {
-                    issuer: args.issuer.clone(),
-                    jwks_url: args.jwks_url.clone(),
-                    authorization_server: args.authorization_server.clone(),
-                    audience: args.audience.clone(),
-                },
-                scopes_supported: vec![],
-            }),
-        )
-        .await
+    Clock {}.handle_with_oauth(&req, oauth_config()).await
 }
 
 ic_cdk::export_candid!();
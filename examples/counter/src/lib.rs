@@ -1,6 +1,8 @@
-use ic_cdk::{init, query, update};
-use ic_http_certification::{HttpRequest, HttpResponse, StatusCode};
-use ic_rmcp::{model::*, schema_for_type, Context, Error, Handler, Server};
+use ic_cdk::{init, post_upgrade, query, update};
+use ic_http_certification::{HeaderField, HttpRequest, HttpResponse};
+use ic_rmcp::{
+    model::*, schema_for_type, tool_not_found, Context, Error, Handler, Server, ToolExt,
+};
 use std::cell::RefCell;
 
 thread_local! {
@@ -10,15 +12,25 @@ thread_local! {
 
 #[init]
 fn init(api_key: String) {
-    API_KEY.with_borrow_mut(|key| *key = api_key)
+    API_KEY.with_borrow_mut(|key| *key = api_key);
+    ic_rmcp::init_certification(&Counter {});
+}
+
+#[post_upgrade]
+fn post_upgrade(api_key: String) {
+    API_KEY.with_borrow_mut(|key| *key = api_key);
+    ic_rmcp::init_certification(&Counter {});
+}
+
+fn is_authorized(headers: &[HeaderField]) -> bool {
+    headers
+        .iter()
+        .any(|(k, v)| k == "x-api-key" && *v == API_KEY.with_borrow(|k| k.clone()))
 }
 
 #[query]
-fn http_request(_: HttpRequest) -> HttpResponse {
-    HttpResponse::builder()
-        .with_status_code(StatusCode::OK)
-        .with_upgrade(true)
-        .build()
+async fn http_request(req: HttpRequest<'_>) -> HttpResponse<'_> {
+    Counter {}.handle_query(&req, is_authorized).await
 }
 
 struct Counter;
@@ -30,12 +42,20 @@ impl Handler for Counter {
             server_info: Implementation {
                 name: "Counter".to_string(),
                 version: "1.0.0".to_string(),
+                title: None,
+                icons: None,
+                website_url: None,
             },
             instructions: Some("This server provides a counter tool that can increase and decrease counter values. The counter starts at 0 and can be modified using the 'increase' and 'decrease' tools. Use 'get_value' to check the current counter.".to_string()),
             ..Default::default()
         }
     }
 
+    // Reading the counter needs no consensus.
+    fn query_tool(&self, name: &str) -> bool {
+        name == "get_value"
+    }
+
     async fn list_tools(
         &self,
         _: Context,
@@ -48,17 +68,20 @@ impl Handler for Counter {
                     "increase",
                     "Increase the counter by 1",
                     schema_for_type::<EmptyObject>(),
-                ),
+                )
+                .destructive(false),
                 Tool::new(
                     "decrease",
                     "Decrease the counter by 1",
                     schema_for_type::<EmptyObject>(),
-                ),
+                )
+                .destructive(false),
                 Tool::new(
                     "get_value",
                     "Get the current value of the counter",
                     schema_for_type::<EmptyObject>(),
-                ),
+                )
+                .read_only(true),
             ],
         })
     }
@@ -93,20 +116,14 @@ impl Handler for Counter {
                     Content::text(value.to_string()).into_contents(),
                 ))
             }
-            _ => Err(Error::invalid_params("not found tool", None)),
+            _ => Err(tool_not_found(&requests.name)),
         }
     }
 }
 
 #[update]
 async fn http_request_update(req: HttpRequest<'_>) -> HttpResponse<'_> {
-    Counter {}
-        .handle(&req, |headers| -> bool {
-            headers
-                .iter()
-                .any(|(k, v)| k == "x-api-key" && *v == API_KEY.with_borrow(|k| k.clone()))
-        })
-        .await
+    Counter {}.handle(&req, is_authorized).await
 }
 
 ic_cdk::export_candid!();
@@ -2,8 +2,10 @@ use ic_cdk::management_canister::{
     http_request_with_closure, HttpMethod, HttpRequestArgs, HttpRequestResult,
 };
 use ic_cdk::{init, query, update};
-use ic_http_certification::{HttpRequest, HttpResponse, StatusCode};
-use ic_rmcp::{model::*, schema_for_type, Context, Error, Handler, Server};
+use ic_http_certification::{HeaderField, HttpRequest, HttpResponse};
+use ic_rmcp::{
+    model::*, schema_for_type, tool_not_found, Context, Error, Handler, Server, ToolError,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::from_slice;
 use std::cell::RefCell;
@@ -87,12 +89,15 @@ async fn fetch_weather(latitude: f64, longitude: f64) -> Result<WeatherResponse,
         .map_err(|err| format!("Failed to parse weather data: {}", err))
 }
 
+fn is_authorized(headers: &[HeaderField]) -> bool {
+    headers
+        .iter()
+        .any(|(k, v)| k == "x-api-key" && *v == API_KEY.with_borrow(|k| k.clone()))
+}
+
 #[query]
-fn http_request(_: HttpRequest) -> HttpResponse {
-    HttpResponse::builder()
-        .with_status_code(StatusCode::OK)
-        .with_upgrade(true)
-        .build()
+async fn http_request(req: HttpRequest<'_>) -> HttpResponse<'_> {
+    Weather {}.handle_query(&req, is_authorized).await
 }
 
 struct Weather;
@@ -104,6 +109,9 @@ impl Handler for Weather {
             server_info: Implementation {
                 name: "Weather".to_string(),
                 version: "1.0.0".to_string(),
+                title: None,
+                icons: None,
+                website_url: None,
             },
             instructions: Some("This server provides weather information using the Open-Meteo API. You can get current weather conditions for any location by providing latitude and longitude coordinates. If no coordinates are provided, it defaults to Berlin, Germany.".to_string()),
             ..Default::default()
@@ -191,23 +199,18 @@ impl Handler for Weather {
                             Content::text(weather_info).into_contents(),
                         ))
                     }
-                    Err(err) => Err(Error::internal_error(err, None)),
+                    // The model can read a failed outcall and retry or report it.
+                    Err(err) => ToolError::new(err).into_result(),
                 }
             }
-            _ => Err(Error::invalid_params("Tool not found", None)),
+            _ => Err(tool_not_found(&requests.name)),
         }
     }
 }
 
 #[update]
 async fn http_request_update(req: HttpRequest<'_>) -> HttpResponse<'_> {
-    Weather {}
-        .handle(&req, |headers| -> bool {
-            headers
-                .iter()
-                .any(|(k, v)| k == "x-api-key" && *v == API_KEY.with_borrow(|k| k.clone()))
-        })
-        .await
+    Weather {}.handle(&req, is_authorized).await
 }
 
 ic_cdk::export_candid!();
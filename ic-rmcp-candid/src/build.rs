@@ -0,0 +1,125 @@
+//! Code generation from `.did` files, for use in `build.rs`.
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("ledger.rs");
+//!     ic_rmcp_candid::build::generate("ledger.did", out).unwrap();
+//! }
+//!
+//! // src/lib.rs
+//! mod ledger {
+//!     include!(concat!(env!("OUT_DIR"), "/ledger.rs"));
+//! }
+//!
+//! impl Handler for Wallet {
+//!     async fn list_tools(&self, _: Context, _: Option<PaginatedRequestParam>) -> Result<ListToolsResult, Error> {
+//!         Ok(ListToolsResult { next_cursor: None, tools: ledger::tools() })
+//!     }
+//!
+//!     async fn call_tool(&self, _: Context, request: CallToolRequestParam) -> Result<CallToolResult, Error> {
+//!         ledger::call(LEDGER_CANISTER_ID, request).await
+//!     }
+//! }
+//! ```
+//!
+//! The generated module contains:
+//! - `CANDID`: the interface text
+//! - `tools()`: one tool per method, with the input schemas computed at build time
+//! - `call(canister, request)`: converts the arguments, calls the method on `canister` and
+//!   converts the reply, as [`CandidInterface::call`] does
+//!
+//! To expose the canister's own methods, pass [`ic_cdk::api::canister_self`] to `call`.
+use crate::CandidInterface;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Generate a Rust module for the service declared in the `.did` file at `did` and write it to
+/// `out`, typically inside `OUT_DIR`.
+///
+/// Fails if the file cannot be read or does not declare a valid service.
+pub fn generate(did: impl AsRef<Path>, out: impl AsRef<Path>) -> io::Result<()> {
+    let did = did.as_ref();
+    println!("cargo:rerun-if-changed={}", did.display());
+
+    let candid = fs::read_to_string(did)?;
+    let interface = CandidInterface::parse(&candid).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {err}", did.display()),
+        )
+    })?;
+
+    fs::write(out, render(&did.display().to_string(), &candid, &interface))
+}
+
+fn render(source: &str, candid: &str, interface: &CandidInterface) -> String {
+    let mut code = String::new();
+    writeln!(
+        code,
+        "// Generated by ic-rmcp-candid from {source}. Do not edit."
+    )
+    .unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "/// The Candid interface of the generated tools.").unwrap();
+    writeln!(code, "pub const CANDID: &str = {candid:?};").unwrap();
+    writeln!(code).unwrap();
+
+    writeln!(code, "/// One MCP tool per method of [`CANDID`].").unwrap();
+    writeln!(
+        code,
+        "pub fn tools() -> ::std::vec::Vec<::ic_rmcp::model::Tool> {{"
+    )
+    .unwrap();
+    writeln!(code, "    ::std::vec![").unwrap();
+    for tool in interface.tools() {
+        let json = serde_json::to_string(&tool).expect("tools serialize to JSON");
+        writeln!(code, "        ::ic_rmcp_candid::__private::tool({json:?}),").unwrap();
+    }
+    writeln!(code, "    ]").unwrap();
+    writeln!(code, "}}").unwrap();
+    writeln!(code).unwrap();
+
+    code.push_str(
+        r#"/// Call the method named by `request` on `canister`, converting JSON arguments to Candid and
+/// the reply back to JSON.
+pub async fn call(
+    canister: ::ic_rmcp_candid::Principal,
+    request: ::ic_rmcp::model::CallToolRequestParam,
+) -> ::std::result::Result<::ic_rmcp::model::CallToolResult, ::ic_rmcp::Error> {
+    ::std::thread_local! {
+        static INTERFACE: ::std::rc::Rc<::ic_rmcp_candid::CandidInterface> = ::std::rc::Rc::new(
+            ::ic_rmcp_candid::CandidInterface::parse(CANDID).expect("checked at build time"),
+        );
+    }
+    let interface = INTERFACE.with(::std::rc::Rc::clone);
+    interface.call(canister, &request.name, request.arguments).await
+}
+"#,
+    );
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let candid = "service : { greet : (text) -> (text) query }";
+        let code = render(
+            "greet.did",
+            candid,
+            &CandidInterface::parse(candid).unwrap(),
+        );
+
+        assert!(code.starts_with("// Generated by ic-rmcp-candid from greet.did. Do not edit."));
+        assert!(code.contains(
+            r#"pub const CANDID: &str = "service : { greet : (text) -> (text) query }";"#
+        ));
+        assert!(code.contains("::ic_rmcp_candid::__private::tool(\"{\\\"name\\\":\\\"greet\\\""));
+        assert!(code.contains("pub async fn call("));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::schema::{is_tuple, label_name, SchemaBuilder};
+use crate::value::{member, to_idl, to_json};
+use candid::types::{FuncMode, Function, Type, TypeEnv, TypeInner};
+use candid::{IDLArgs, Principal};
+use candid_parser::utils::CandidSource;
+use ic_cdk::call::Call;
+use ic_rmcp::{model::*, tool_not_found, Error};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// The methods of a Candid service, described as MCP tools.
+///
+/// Each method becomes a tool of the same name. A method taking a single record is called with
+/// that record's fields as tool arguments; other methods take their arguments as `arg0`, `arg1`,
+/// and so on. Query methods are annotated as read-only, and update methods as destructive, so
+/// that [`Handler::destructive_scope`](ic_rmcp::Handler::destructive_scope) guards them.
+#[derive(Debug, Clone)]
+pub struct CandidInterface {
+    env: TypeEnv,
+    methods: Vec<(String, Function)>,
+}
+
+impl CandidInterface {
+    /// Parse the text of a `.did` file declaring a service.
+    pub fn parse(did: &str) -> Result<Self, String> {
+        let (env, actor) = CandidSource::Text(did)
+            .load()
+            .map_err(|err| err.to_string())?;
+        let actor = actor.ok_or("Candid interface declares no service")?;
+        let methods = env
+            .as_service(&actor)
+            .map_err(|err| err.to_string())?
+            .iter()
+            .map(|(name, ty)| Ok((name.clone(), env.as_func(ty)?.clone())))
+            .collect::<Result<_, candid::Error>>()
+            .map_err(|err| err.to_string())?;
+        Ok(CandidInterface { env, methods })
+    }
+
+    /// Names of the service's methods, sorted by name as Candid stores them.
+    pub fn methods(&self) -> impl Iterator<Item = &str> {
+        self.methods.iter().map(|(name, _)| name.as_str())
+    }
+
+    fn function(&self, method: &str) -> Option<&Function> {
+        self.methods
+            .iter()
+            .find(|(name, _)| name == method)
+            .map(|(_, func)| func)
+    }
+
+    /// The record type of a method taking a single record with named fields.
+    fn record_argument(&self, func: &Function) -> Option<Type> {
+        match func.args.as_slice() {
+            [arg] => self
+                .env
+                .trace_type(arg)
+                .ok()
+                .filter(|ty| match ty.as_ref() {
+                    TypeInner::Record(fields) => !is_tuple(fields),
+                    _ => false,
+                }),
+            _ => None,
+        }
+    }
+
+    /// The tool describing `method`, if the service has it.
+    pub fn tool(&self, method: &str) -> Option<Tool> {
+        let func = self.function(method)?;
+        let mut builder = SchemaBuilder::new(&self.env);
+        let input_schema = match self.record_argument(func).as_ref().map(Type::as_ref) {
+            Some(TypeInner::Record(fields)) => {
+                let fields: Vec<(String, Type)> = fields
+                    .iter()
+                    .map(|f| (label_name(&f.id), f.ty.clone()))
+                    .collect();
+                builder.object(&fields)
+            }
+            _ => {
+                let args: Vec<(String, Type)> = func
+                    .args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| (format!("arg{i}"), ty.clone()))
+                    .collect();
+                builder.object(&args)
+            }
+        };
+
+        let signature = |types: &[Type]| {
+            types
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut description = format!(
+            "Candid method {method} : ({}) -> ({})",
+            signature(&func.args),
+            signature(&func.rets)
+        );
+        for mode in &func.modes {
+            description.push_str(match mode {
+                FuncMode::Query => " query",
+                FuncMode::CompositeQuery => " composite_query",
+                FuncMode::Oneway => " oneway",
+            });
+        }
+
+        Some(Tool {
+            name: method.to_string().into(),
+            title: None,
+            description: Some(description.into()),
+            input_schema: Arc::new(builder.finish(input_schema)),
+            output_schema: None,
+            annotations: Some(match func.is_query() {
+                true => ToolAnnotations {
+                    read_only_hint: Some(true),
+                    ..Default::default()
+                },
+                false => ToolAnnotations {
+                    read_only_hint: Some(false),
+                    destructive_hint: Some(true),
+                    ..Default::default()
+                },
+            }),
+            icons: None,
+        })
+    }
+
+    /// Tools for all methods, sorted by method name.
+    pub fn tools(&self) -> Vec<Tool> {
+        self.methods()
+            .filter_map(|method| self.tool(method))
+            .collect()
+    }
+
+    /// Encode tool arguments as the Candid arguments of `method`.
+    ///
+    /// Arguments that do not match the method's types yield `invalid_params`.
+    pub fn encode_args(
+        &self,
+        method: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<u8>, Error> {
+        let func = self
+            .function(method)
+            .ok_or_else(|| tool_not_found(method))?;
+        let arguments = arguments.unwrap_or_default();
+        let invalid = |err: String| {
+            Error::invalid_params(format!("invalid arguments to tool {method}: {err}"), None)
+        };
+
+        let values = match self.record_argument(func) {
+            Some(record) => {
+                vec![to_idl(&self.env, &record, &Value::Object(arguments), "").map_err(invalid)?]
+            }
+            None => func
+                .args
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| member(&self.env, ty, &arguments, &format!("arg{i}"), ""))
+                .collect::<Result<_, _>>()
+                .map_err(invalid)?,
+        };
+
+        IDLArgs::new(&values)
+            .to_bytes_with_types(&self.env, &func.args)
+            .map_err(|err| invalid(err.to_string()))
+    }
+
+    /// Decode the Candid reply of `method` as JSON: `null` for no values, the value itself for
+    /// one, and an array otherwise.
+    pub fn decode_reply(&self, method: &str, reply: &[u8]) -> Result<Value, Error> {
+        let func = self
+            .function(method)
+            .ok_or_else(|| tool_not_found(method))?;
+        let reply = IDLArgs::from_bytes_with_types(reply, &self.env, &func.rets)
+            .map_err(|err| Error::internal_error(format!("decode reply: {err}"), None))?;
+        Ok(match reply.args.as_slice() {
+            [] => Value::Null,
+            [value] => to_json(value),
+            values => Value::Array(values.iter().map(to_json).collect()),
+        })
+    }
+
+    /// Call `method` on `canister` with JSON arguments and return its reply as JSON text.
+    ///
+    /// Use [`ic_cdk::api::canister_self`] as `canister` to expose the canister's own methods.
+    /// Rejected calls are reported as a tool result with `isError` set; oneway methods return an
+    /// empty result as soon as the call is sent.
+    pub async fn call(
+        &self,
+        canister: Principal,
+        method: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<CallToolResult, Error> {
+        let func = self
+            .function(method)
+            .ok_or_else(|| tool_not_found(method))?;
+        let args = self.encode_args(method, arguments)?;
+        let call = Call::unbounded_wait(canister, method).with_raw_args(&args);
+
+        if func.modes.contains(&FuncMode::Oneway) {
+            return match call.oneway() {
+                Ok(()) => Ok(CallToolResult::success(vec![])),
+                Err(err) => Ok(CallToolResult::error(vec![Content::text(format!(
+                    "call to {method} failed: {err}"
+                ))])),
+            };
+        }
+
+        match call.await {
+            Ok(response) => {
+                let reply = self.decode_reply(method, &response.into_bytes())?;
+                Ok(CallToolResult::success(vec![Content::text(
+                    reply.to_string(),
+                )]))
+            }
+            Err(err) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "call to {method} failed: {err}"
+            ))])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_rmcp::ToolExt;
+    use serde_json::json;
+
+    const DID: &str = r#"
+        type Account = record { owner : principal; subaccount : opt blob };
+        service : {
+            balance_of : (Account) -> (nat) query;
+            transfer : (principal, nat) -> (variant { Ok : nat; Err : text });
+        }
+    "#;
+
+    #[test]
+    fn test_tools() {
+        let interface = CandidInterface::parse(DID).unwrap();
+        let mut methods: Vec<&str> = interface.methods().collect();
+        methods.sort();
+        assert_eq!(methods, vec!["balance_of", "transfer"]);
+
+        let balance_of = interface.tool("balance_of").unwrap();
+        assert!(balance_of.is_read_only());
+        assert_eq!(
+            Value::Object(balance_of.input_schema.as_ref().clone()),
+            json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"type": "string", "description": "Principal in textual form"},
+                    "subaccount": {
+                        "type": "string",
+                        "description": "Hex-encoded bytes",
+                        "nullable": true
+                    }
+                },
+                "required": ["owner"]
+            })
+        );
+
+        let transfer = interface.tool("transfer").unwrap();
+        assert!(transfer.is_destructive());
+        assert!(!balance_of.is_destructive());
+        assert_eq!(
+            transfer.input_schema.get("required"),
+            Some(&json!(["arg0", "arg1"]))
+        );
+        assert!(interface.tool("mint").is_none());
+    }
+
+    #[test]
+    fn test_encode_args() {
+        let interface = CandidInterface::parse(DID).unwrap();
+
+        let args = interface
+            .encode_args(
+                "transfer",
+                json!({"arg0": "aaaaa-aa", "arg1": 5}).as_object().cloned(),
+            )
+            .unwrap();
+        let (to, amount): (Principal, candid::Nat) = candid::decode_args(&args).unwrap();
+        assert_eq!(
+            (to, amount),
+            (Principal::management_canister(), candid::Nat::from(5u64))
+        );
+
+        assert_eq!(
+            interface.encode_args("transfer", json!({"arg0": "aaaaa-aa"}).as_object().cloned()),
+            Err(Error::invalid_params(
+                "invalid arguments to tool transfer: /arg1 is required",
+                None
+            ))
+        );
+        assert_eq!(
+            interface.encode_args("mint", None),
+            Err(tool_not_found("mint"))
+        );
+
+        let reply = candid::encode_one(candid::Nat::from(7u64)).unwrap();
+        assert_eq!(interface.decode_reply("balance_of", &reply), Ok(json!(7)));
+    }
+}
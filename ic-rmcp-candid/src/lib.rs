@@ -0,0 +1,37 @@
+//! ic-rmcp-candid: MCP tools generated from the Candid interface of a canister.
+//!
+//! Every method of a Candid service becomes a tool whose input schema is derived from the
+//! method's argument types. Tool arguments are converted from JSON to Candid values, the method
+//! is called, and the reply is converted back to JSON.
+//!
+//! - [`CandidInterface`] parses a `.did` file at runtime
+//! - [`build::generate`] does the same from `build.rs`, computing the schemas at build time
+//! - [`CandidProxy`] is a ready-made handler exposing selected methods of another canister
+//!
+//! Interfaces and generated code work for the canister's own methods (call
+//! [`ic_cdk::api::canister_self`]) and for methods on other canisters.
+
+mod interface;
+/// Tools for the methods of a Candid service.
+pub use interface::CandidInterface;
+
+mod proxy;
+/// A handler forwarding tool calls to another canister.
+pub use proxy::CandidProxy;
+
+mod schema;
+mod value;
+
+pub mod build;
+
+pub use candid::Principal;
+
+#[doc(hidden)]
+pub mod __private {
+    use ic_rmcp::model::Tool;
+
+    /// Deserialize a tool description emitted by [`crate::build::generate`].
+    pub fn tool(json: &str) -> Tool {
+        serde_json::from_str(json).expect("generated tool descriptions are valid")
+    }
+}
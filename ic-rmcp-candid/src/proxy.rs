@@ -0,0 +1,219 @@
+//! A handler forwarding tool calls to the Candid methods of another canister.
+//!
+//! ```ignore
+//! thread_local! {
+//!     static PROXY: RefCell<Option<&'static CandidProxy>> = const { RefCell::new(None) };
+//! }
+//!
+//! #[init]
+//! fn init(ledger: Principal, did: String) {
+//!     let proxy = CandidProxy::new(ledger, &did)
+//!         .expect("valid Candid interface")
+//!         .expose(["icrc1_balance_of", "icrc1_transfer"]);
+//!     PROXY.with_borrow_mut(|p| *p = Some(Box::leak(Box::new(proxy))));
+//! }
+//! ```
+//!
+//! Mount several proxies in an `ic_rmcp::Compose` to front an application made of many
+//! canisters from a single MCP server. Keep the interface text in stable memory to rebuild the
+//! proxy in `post_upgrade`.
+use crate::CandidInterface;
+use candid::Principal;
+use ic_rmcp::{model::*, tool_not_found, Context, Error, Handler, ServerConfig};
+
+/// Exposes selected methods of a canister as tools, calling the canister with Candid arguments
+/// built from the JSON arguments and returning its reply as JSON.
+///
+/// No method is exposed until [`CandidProxy::expose`] selects some, so a new method of the
+/// proxied canister never becomes callable by MCP clients on its own; [`CandidProxy::expose_all`]
+/// opts out of that. Update methods are annotated as destructive, so
+/// [`CandidProxy::with_destructive_scope`] can require an extra OAuth scope to call them.
+///
+/// Calls always reach the update endpoint: Candid query methods are annotated read-only, but
+/// forwarding them is an inter-canister call, which a query cannot make.
+#[derive(Debug, Clone)]
+pub struct CandidProxy {
+    info: ServerInfo,
+    config: ServerConfig,
+    destructive_scope: Option<String>,
+    canister: Principal,
+    interface: CandidInterface,
+    /// Selected methods, or `None` after [`CandidProxy::expose_all`].
+    exposed: Option<Vec<String>>,
+}
+
+impl CandidProxy {
+    /// Create a proxy for `canister`, whose interface is given as the text of a `.did` file.
+    ///
+    /// The proxy exposes no methods until [`CandidProxy::expose`] is called.
+    pub fn new(canister: Principal, did: &str) -> Result<Self, String> {
+        Ok(CandidProxy {
+            info: ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            },
+            config: ServerConfig::default(),
+            destructive_scope: None,
+            canister,
+            interface: CandidInterface::parse(did)?,
+            exposed: Some(vec![]),
+        })
+    }
+
+    /// Set the server info returned on `initialize`.
+    pub fn with_info(mut self, info: ServerInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Set the paths the proxy is served at, for example one per proxied canister.
+    pub fn with_config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Require `scope` to call update methods; see
+    /// [`Handler::destructive_scope`](ic_rmcp::Handler::destructive_scope).
+    pub fn with_destructive_scope(mut self, scope: impl Into<String>) -> Self {
+        self.destructive_scope = Some(scope.into());
+        self
+    }
+
+    /// Expose the named methods, replacing any earlier selection. Names the interface does not
+    /// declare are ignored.
+    pub fn expose<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exposed = Some(methods.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Expose every method of the interface, including any added to it later.
+    ///
+    /// Anyone allowed to call the MCP server can then make the proxy call any of these methods
+    /// with its own identity, so prefer [`CandidProxy::expose`] unless the proxied canister
+    /// guards every method itself.
+    pub fn expose_all(mut self) -> Self {
+        self.exposed = None;
+        self
+    }
+
+    /// The canister receiving the calls.
+    pub fn canister(&self) -> Principal {
+        self.canister
+    }
+
+    /// Whether `method` is declared by the interface and selected for exposure.
+    pub fn is_exposed(&self, method: &str) -> bool {
+        self.interface.methods().any(|m| m == method)
+            && self
+                .exposed
+                .as_ref()
+                .is_none_or(|exposed| exposed.iter().any(|m| m == method))
+    }
+
+    /// Tools for the exposed methods, sorted by method name.
+    pub fn tools(&self) -> Vec<Tool> {
+        self.interface
+            .methods()
+            .filter(|method| self.is_exposed(method))
+            .filter_map(|method| self.interface.tool(method))
+            .collect()
+    }
+}
+
+impl Handler for CandidProxy {
+    fn get_info(&self, _: Context) -> ServerInfo {
+        self.info.clone()
+    }
+
+    fn server_config(&self) -> ServerConfig {
+        self.config.clone()
+    }
+
+    fn destructive_scope(&self) -> Option<&str> {
+        self.destructive_scope.as_deref()
+    }
+
+    async fn list_tools(
+        &self,
+        _: Context,
+        _: Option<PaginatedRequestParam>,
+    ) -> Result<ListToolsResult, Error> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: self.tools(),
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        _: Context,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, Error> {
+        if !self.is_exposed(&request.name) {
+            return Err(tool_not_found(&request.name));
+        }
+        self.interface
+            .call(self.canister, &request.name, request.arguments)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use ic_rmcp::ToolExt;
+
+    const DID: &str = r#"
+        service : {
+            greet : (text) -> (text) query;
+            set_owner : (principal) -> ();
+            reset : () -> ();
+        }
+    "#;
+
+    #[test]
+    fn test_expose() {
+        let proxy = CandidProxy::new(Principal::anonymous(), DID).unwrap();
+        assert!(proxy.tools().is_empty());
+        assert!(!proxy.is_exposed("greet"));
+
+        let all = proxy.clone().expose_all();
+        assert_eq!(all.tools().len(), 3);
+        assert!(all.is_exposed("set_owner"));
+
+        let proxy = proxy.expose(["greet", "reset", "missing"]);
+        let mut names: Vec<_> = proxy
+            .tools()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["greet", "reset"]);
+        assert!(!proxy.is_exposed("set_owner"));
+        assert!(!proxy.is_exposed("missing"));
+
+        let destructive: Vec<_> = proxy
+            .tools()
+            .into_iter()
+            .filter(|tool| tool.is_destructive())
+            .map(|tool| tool.name.to_string())
+            .collect();
+        assert_eq!(destructive, vec!["reset"]);
+
+        assert_eq!(
+            block_on(proxy.call_tool(
+                Context::default(),
+                CallToolRequestParam {
+                    name: "set_owner".into(),
+                    arguments: None,
+                },
+            )),
+            Err(tool_not_found("set_owner"))
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Candid types to JSON Schema.
+//!
+//! The schemas describe the JSON accepted by [`to_idl`](crate::value::to_idl):
+//! - `nat`, `int`, `nat64` and `int64` take an integer or a decimal string
+//! - `blob` (`vec nat8`) takes a hex string
+//! - `opt T` is `T` marked `nullable`, like the schemas of [`ic_rmcp::schema_for_type`]
+//! - tuples are arrays, records are objects keyed by field name
+//! - variants are single-key objects such as `{"Ok": 1}`; tags without payload may be given as a
+//!   plain string
+//! - named types become `definitions`, so recursive types are supported
+use candid::types::{Field, Label, Type, TypeEnv, TypeInner};
+use serde_json::{json, Map, Value};
+
+pub(crate) struct SchemaBuilder<'a> {
+    env: &'a TypeEnv,
+    definitions: Map<String, Value>,
+}
+
+impl<'a> SchemaBuilder<'a> {
+    pub(crate) fn new(env: &'a TypeEnv) -> Self {
+        SchemaBuilder {
+            env,
+            definitions: Map::new(),
+        }
+    }
+
+    /// Attach the collected `definitions` to a root schema.
+    pub(crate) fn finish(self, mut root: Map<String, Value>) -> Map<String, Value> {
+        if !self.definitions.is_empty() {
+            root.insert("definitions".to_string(), Value::Object(self.definitions));
+        }
+        root
+    }
+
+    /// Object schema for named values, such as record fields or method arguments.
+    pub(crate) fn object(&mut self, fields: &[(String, Type)]) -> Map<String, Value> {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for (name, ty) in fields {
+            properties.insert(name.clone(), self.schema(ty));
+            if !is_optional(self.env, ty) {
+                required.push(Value::String(name.clone()));
+            }
+        }
+
+        // Candid orders fields by label hash; list them alphabetically instead.
+        required.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+
+        let mut object = Map::new();
+        object.insert("type".to_string(), json!("object"));
+        object.insert("properties".to_string(), Value::Object(properties));
+        if !required.is_empty() {
+            object.insert("required".to_string(), Value::Array(required));
+        }
+        object
+    }
+
+    pub(crate) fn schema(&mut self, ty: &Type) -> Value {
+        match ty.as_ref() {
+            TypeInner::Null => json!({"type": "null"}),
+            TypeInner::Bool => json!({"type": "boolean"}),
+            TypeInner::Text => json!({"type": "string"}),
+            TypeInner::Float32 | TypeInner::Float64 => json!({"type": "number"}),
+            TypeInner::Nat8 => bounded(0, u8::MAX as i64),
+            TypeInner::Nat16 => bounded(0, u16::MAX as i64),
+            TypeInner::Nat32 => bounded(0, u32::MAX as i64),
+            TypeInner::Int8 => bounded(i8::MIN as i64, i8::MAX as i64),
+            TypeInner::Int16 => bounded(i16::MIN as i64, i16::MAX as i64),
+            TypeInner::Int32 => bounded(i32::MIN as i64, i32::MAX as i64),
+            TypeInner::Nat | TypeInner::Nat64 => json!({
+                "type": ["integer", "string"],
+                "minimum": 0,
+                "description": "Natural number; pass large values as a decimal string"
+            }),
+            TypeInner::Int | TypeInner::Int64 => json!({
+                "type": ["integer", "string"],
+                "description": "Integer; pass large values as a decimal string"
+            }),
+            TypeInner::Principal => json!({
+                "type": "string",
+                "description": "Principal in textual form"
+            }),
+            TypeInner::Reserved => Value::Bool(true),
+            TypeInner::Empty => Value::Bool(false),
+            TypeInner::Opt(inner) => {
+                let schema = self.schema(inner);
+                match schema {
+                    Value::Object(mut schema) if !schema.contains_key("$ref") => {
+                        schema.insert("nullable".to_string(), Value::Bool(true));
+                        Value::Object(schema)
+                    }
+                    schema => json!({"allOf": [schema], "nullable": true}),
+                }
+            }
+            TypeInner::Vec(inner) if is_nat8(self.env, inner) => json!({
+                "type": "string",
+                "description": "Hex-encoded bytes"
+            }),
+            TypeInner::Vec(inner) => json!({"type": "array", "items": self.schema(inner)}),
+            TypeInner::Record(fields) if is_tuple(fields) => {
+                let items: Vec<Value> = fields.iter().map(|f| self.schema(&f.ty)).collect();
+                json!({
+                    "type": "array",
+                    "items": items,
+                    "minItems": fields.len(),
+                    "maxItems": fields.len()
+                })
+            }
+            TypeInner::Record(fields) => {
+                let fields: Vec<(String, Type)> = fields
+                    .iter()
+                    .map(|f| (label_name(&f.id), f.ty.clone()))
+                    .collect();
+                Value::Object(self.object(&fields))
+            }
+            TypeInner::Variant(fields) => {
+                let mut options = Vec::new();
+                let mut tags = Vec::new();
+                for field in fields {
+                    let tag = label_name(&field.id);
+                    if is(self.env, &field.ty, |ty| matches!(ty, TypeInner::Null)) {
+                        tags.push(Value::String(tag.clone()));
+                    }
+                    options.push(json!({
+                        "type": "object",
+                        "properties": {tag.clone(): self.schema(&field.ty)},
+                        "required": [tag],
+                        "additionalProperties": false
+                    }));
+                }
+                if !tags.is_empty() {
+                    options.push(json!({"type": "string", "enum": tags}));
+                }
+                json!({"oneOf": options})
+            }
+            TypeInner::Service(_) => json!({
+                "type": "string",
+                "description": "Canister principal in textual form"
+            }),
+            TypeInner::Func(_) => json!({
+                "type": "object",
+                "properties": {
+                    "principal": {"type": "string"},
+                    "method": {"type": "string"}
+                },
+                "required": ["principal", "method"]
+            }),
+            TypeInner::Var(name) => {
+                if !self.definitions.contains_key(name) {
+                    // Placeholder first, so that recursive types terminate.
+                    self.definitions.insert(name.clone(), Value::Bool(true));
+                    let env = self.env;
+                    let schema = match env.find_type(name) {
+                        Ok(ty) => self.schema(ty),
+                        Err(_) => Value::Bool(true),
+                    };
+                    self.definitions.insert(name.clone(), schema);
+                }
+                json!({"$ref": format!("#/definitions/{name}")})
+            }
+            _ => Value::Bool(true),
+        }
+    }
+}
+
+fn bounded(minimum: i64, maximum: i64) -> Value {
+    json!({"type": "integer", "minimum": minimum, "maximum": maximum})
+}
+
+/// The JSON name of a record field or variant tag.
+pub(crate) fn label_name(label: &Label) -> String {
+    match label {
+        Label::Named(name) => name.clone(),
+        Label::Id(id) | Label::Unnamed(id) => id.to_string(),
+    }
+}
+
+/// Whether a record's fields are unnamed, as in `record { text; nat }`.
+pub(crate) fn is_tuple(fields: &[Field]) -> bool {
+    !fields.is_empty()
+        && fields
+            .iter()
+            .all(|f| matches!(f.id.as_ref(), Label::Unnamed(_)))
+}
+
+/// Whether a value of this type may be omitted from JSON.
+pub(crate) fn is_optional(env: &TypeEnv, ty: &Type) -> bool {
+    is(env, ty, |ty| {
+        matches!(
+            ty,
+            TypeInner::Opt(_) | TypeInner::Null | TypeInner::Reserved
+        )
+    })
+}
+
+pub(crate) fn is_nat8(env: &TypeEnv, ty: &Type) -> bool {
+    is(env, ty, |ty| matches!(ty, TypeInner::Nat8))
+}
+
+/// Check the type behind any type names.
+fn is(env: &TypeEnv, ty: &Type, check: impl Fn(&TypeInner) -> bool) -> bool {
+    env.trace_type(ty).is_ok_and(|ty| check(ty.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid_parser::utils::CandidSource;
+
+    fn schema(did: &str, name: &str) -> Value {
+        let (env, _) = CandidSource::Text(did).load().unwrap();
+        let mut builder = SchemaBuilder::new(&env);
+        let schema = builder.schema(env.find_type(name).unwrap());
+        let mut root = Map::new();
+        root.insert("schema".to_string(), schema);
+        Value::Object(builder.finish(root))
+    }
+
+    #[test]
+    fn test_record() {
+        assert_eq!(
+            schema(
+                "type Transfer = record { to : principal; amount : nat; memo : opt blob };",
+                "Transfer"
+            ),
+            json!({"schema": {
+                "type": "object",
+                "properties": {
+                    "amount": {
+                        "type": ["integer", "string"],
+                        "minimum": 0,
+                        "description": "Natural number; pass large values as a decimal string"
+                    },
+                    "memo": {"type": "string", "description": "Hex-encoded bytes", "nullable": true},
+                    "to": {"type": "string", "description": "Principal in textual form"}
+                },
+                "required": ["amount", "to"]
+            }})
+        );
+    }
+
+    #[test]
+    fn test_variant_and_recursion() {
+        let schema = schema(
+            "type List = opt record { nat8; List }; type Result = variant { Ok : List; Err };",
+            "Result",
+        );
+
+        let options = schema["schema"]["oneOf"].as_array().unwrap();
+        assert_eq!(options.len(), 3);
+        assert!(options.contains(&json!({
+            "type": "object",
+            "properties": {"Ok": {"$ref": "#/definitions/List"}},
+            "required": ["Ok"],
+            "additionalProperties": false
+        })));
+        assert!(options.contains(&json!({
+            "type": "object",
+            "properties": {"Err": {"type": "null"}},
+            "required": ["Err"],
+            "additionalProperties": false
+        })));
+        assert!(options.contains(&json!({"type": "string", "enum": ["Err"]})));
+
+        assert_eq!(
+            schema["definitions"],
+            json!({"List": {
+                "type": "array",
+                "items": [
+                    {"type": "integer", "minimum": 0, "maximum": 255},
+                    {"$ref": "#/definitions/List"}
+                ],
+                "minItems": 2,
+                "maxItems": 2,
+                "nullable": true
+            }})
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Conversion between JSON and Candid values, following the mapping described in
+//! [`schema`](crate::schema).
+use crate::schema::{is_nat8, is_optional, is_tuple, label_name};
+use candid::types::value::{IDLField, IDLValue, VariantValue};
+use candid::types::{Label, Type, TypeEnv, TypeInner};
+use candid::{Int, Nat, Principal};
+use serde_json::{json, Map, Value};
+
+/// Largest integer a JSON number holds exactly in common clients (2^53 - 1).
+const MAX_SAFE_INTEGER: i128 = 9_007_199_254_740_991;
+
+/// Convert a JSON value to a Candid value of type `ty`.
+///
+/// `pointer` is the JSON pointer of `value`, used in error messages.
+pub(crate) fn to_idl(
+    env: &TypeEnv,
+    ty: &Type,
+    value: &Value,
+    pointer: &str,
+) -> Result<IDLValue, String> {
+    let ty = env.trace_type(ty).map_err(|err| err.to_string())?;
+    let mismatch = || format!("{} must be {}", at(pointer), ty);
+
+    let idl = match (ty.as_ref(), value) {
+        (TypeInner::Null, Value::Null) => IDLValue::Null,
+        (TypeInner::Reserved, _) => IDLValue::Reserved,
+        (TypeInner::Bool, Value::Bool(b)) => IDLValue::Bool(*b),
+        (TypeInner::Text, Value::String(s)) => IDLValue::Text(s.clone()),
+        (TypeInner::Nat, _) => IDLValue::Nat(
+            match value {
+                Value::Number(n) => n.as_u64().map(Nat::from),
+                Value::String(s) => s.parse::<Nat>().ok(),
+                _ => None,
+            }
+            .ok_or_else(mismatch)?,
+        ),
+        (TypeInner::Int, _) => IDLValue::Int(
+            match value {
+                Value::Number(n) => n.as_i64().map(Int::from),
+                Value::String(s) => s.parse::<Int>().ok(),
+                _ => None,
+            }
+            .ok_or_else(mismatch)?,
+        ),
+        (TypeInner::Nat8, _) => IDLValue::Nat8(unsigned(value).ok_or_else(mismatch)?),
+        (TypeInner::Nat16, _) => IDLValue::Nat16(unsigned(value).ok_or_else(mismatch)?),
+        (TypeInner::Nat32, _) => IDLValue::Nat32(unsigned(value).ok_or_else(mismatch)?),
+        (TypeInner::Nat64, _) => IDLValue::Nat64(
+            value
+                .as_u64()
+                .or_else(|| value.as_str()?.parse().ok())
+                .ok_or_else(mismatch)?,
+        ),
+        (TypeInner::Int8, _) => IDLValue::Int8(signed(value).ok_or_else(mismatch)?),
+        (TypeInner::Int16, _) => IDLValue::Int16(signed(value).ok_or_else(mismatch)?),
+        (TypeInner::Int32, _) => IDLValue::Int32(signed(value).ok_or_else(mismatch)?),
+        (TypeInner::Int64, _) => IDLValue::Int64(
+            value
+                .as_i64()
+                .or_else(|| value.as_str()?.parse().ok())
+                .ok_or_else(mismatch)?,
+        ),
+        (TypeInner::Float32, Value::Number(n)) => {
+            IDLValue::Float32(n.as_f64().ok_or_else(mismatch)? as f32)
+        }
+        (TypeInner::Float64, Value::Number(n)) => {
+            IDLValue::Float64(n.as_f64().ok_or_else(mismatch)?)
+        }
+        (TypeInner::Principal, Value::String(s)) => {
+            IDLValue::Principal(Principal::from_text(s).map_err(|_| mismatch())?)
+        }
+        (TypeInner::Service(_), Value::String(s)) => {
+            IDLValue::Service(Principal::from_text(s).map_err(|_| mismatch())?)
+        }
+        (TypeInner::Func(_), Value::Object(object)) => {
+            let principal = object.get("principal").and_then(Value::as_str);
+            let method = object.get("method").and_then(Value::as_str);
+            match (principal.map(Principal::from_text), method) {
+                (Some(Ok(principal)), Some(method)) => {
+                    IDLValue::Func(principal, method.to_string())
+                }
+                _ => return Err(mismatch()),
+            }
+        }
+        (TypeInner::Opt(_), Value::Null) => IDLValue::None,
+        (TypeInner::Opt(inner), _) => IDLValue::Opt(Box::new(to_idl(env, inner, value, pointer)?)),
+        (TypeInner::Vec(inner), Value::String(hex)) if is_nat8(env, inner) => {
+            IDLValue::Blob(decode_hex(hex).ok_or_else(mismatch)?)
+        }
+        (TypeInner::Vec(inner), Value::Array(items)) => IDLValue::Vec(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| to_idl(env, inner, item, &format!("{pointer}/{i}")))
+                .collect::<Result<_, _>>()?,
+        ),
+        (TypeInner::Record(fields), Value::Array(items)) if is_tuple(fields) => {
+            if items.len() != fields.len() {
+                return Err(mismatch());
+            }
+            IDLValue::Record(
+                fields
+                    .iter()
+                    .zip(items)
+                    .enumerate()
+                    .map(|(i, (field, item))| {
+                        Ok(IDLField {
+                            id: field.id.as_ref().clone(),
+                            val: to_idl(env, &field.ty, item, &format!("{pointer}/{i}"))?,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?,
+            )
+        }
+        (TypeInner::Record(fields), Value::Object(object)) => IDLValue::Record(
+            fields
+                .iter()
+                .map(|field| {
+                    let name = label_name(&field.id);
+                    Ok(IDLField {
+                        id: field.id.as_ref().clone(),
+                        val: member(env, &field.ty, object, &name, pointer)?,
+                    })
+                })
+                .collect::<Result<_, String>>()?,
+        ),
+        (TypeInner::Variant(fields), Value::Object(_) | Value::String(_)) => {
+            let (tag, payload) = match value {
+                Value::String(tag) => (tag.as_str(), &Value::Null),
+                Value::Object(object) if object.len() == 1 => {
+                    let (tag, payload) = object.iter().next().expect("one entry");
+                    (tag.as_str(), payload)
+                }
+                _ => return Err(mismatch()),
+            };
+            let (index, field) = fields
+                .iter()
+                .enumerate()
+                .find(|(_, field)| label_name(&field.id) == tag)
+                .ok_or_else(mismatch)?;
+            IDLValue::Variant(VariantValue(
+                Box::new(IDLField {
+                    id: field.id.as_ref().clone(),
+                    val: to_idl(env, &field.ty, payload, &format!("{pointer}/{tag}"))?,
+                }),
+                index as u64,
+            ))
+        }
+        _ => return Err(mismatch()),
+    };
+    Ok(idl)
+}
+
+/// Convert the member `name` of a JSON object, allowing it to be omitted when its type is
+/// optional.
+pub(crate) fn member(
+    env: &TypeEnv,
+    ty: &Type,
+    object: &Map<String, Value>,
+    name: &str,
+    pointer: &str,
+) -> Result<IDLValue, String> {
+    let pointer = format!("{pointer}/{}", name.replace('~', "~0").replace('/', "~1"));
+    match object.get(name) {
+        Some(value) => to_idl(env, ty, value, &pointer),
+        None if is_optional(env, ty) => to_idl(env, ty, &Value::Null, &pointer),
+        None => Err(format!("{pointer} is required")),
+    }
+}
+
+/// Convert a Candid value to JSON.
+pub(crate) fn to_json(value: &IDLValue) -> Value {
+    match value {
+        IDLValue::Null | IDLValue::None | IDLValue::Reserved => Value::Null,
+        IDLValue::Bool(b) => Value::Bool(*b),
+        IDLValue::Text(s) | IDLValue::Number(s) => Value::String(s.clone()),
+        IDLValue::Float32(f) => json!(f),
+        IDLValue::Float64(f) => json!(f),
+        IDLValue::Nat(n) => integer(n.0.to_string()),
+        IDLValue::Int(n) => integer(n.0.to_string()),
+        IDLValue::Nat8(n) => json!(n),
+        IDLValue::Nat16(n) => json!(n),
+        IDLValue::Nat32(n) => json!(n),
+        IDLValue::Nat64(n) => integer(n.to_string()),
+        IDLValue::Int8(n) => json!(n),
+        IDLValue::Int16(n) => json!(n),
+        IDLValue::Int32(n) => json!(n),
+        IDLValue::Int64(n) => integer(n.to_string()),
+        IDLValue::Principal(p) | IDLValue::Service(p) => Value::String(p.to_text()),
+        IDLValue::Func(p, method) => json!({"principal": p.to_text(), "method": method}),
+        IDLValue::Opt(value) => to_json(value),
+        IDLValue::Blob(bytes) => Value::String(encode_hex(bytes)),
+        IDLValue::Vec(items) => Value::Array(items.iter().map(to_json).collect()),
+        IDLValue::Record(fields) => {
+            let tuple =
+                !fields.is_empty() && fields.iter().all(|f| matches!(f.id, Label::Unnamed(_)));
+            if tuple {
+                Value::Array(fields.iter().map(|f| to_json(&f.val)).collect())
+            } else {
+                Value::Object(
+                    fields
+                        .iter()
+                        .map(|f| (label_name(&f.id), to_json(&f.val)))
+                        .collect(),
+                )
+            }
+        }
+        IDLValue::Variant(VariantValue(field, _)) => {
+            let mut object = Map::new();
+            object.insert(label_name(&field.id), to_json(&field.val));
+            Value::Object(object)
+        }
+    }
+}
+
+/// A JSON number when it is exactly representable, otherwise a decimal string.
+fn integer(digits: String) -> Value {
+    match digits.parse::<i128>() {
+        Ok(n) if n.abs() <= MAX_SAFE_INTEGER => json!(n as i64),
+        _ => Value::String(digits),
+    }
+}
+
+fn unsigned<T: TryFrom<u64>>(value: &Value) -> Option<T> {
+    value.as_u64().and_then(|n| T::try_from(n).ok())
+}
+
+fn signed<T: TryFrom<i64>>(value: &Value) -> Option<T> {
+    value.as_i64().and_then(|n| T::try_from(n).ok())
+}
+
+fn at(pointer: &str) -> &str {
+    match pointer {
+        "" => "arguments",
+        pointer => pointer,
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid_parser::utils::CandidSource;
+
+    const DID: &str = r#"
+        type Transfer = record { to : principal; amount : nat; memo : opt blob };
+        type Result = variant { Ok : nat64; Err : text; Pending };
+        type Pair = record { text; int8 };
+    "#;
+
+    fn round_trip(name: &str, json: Value) -> Result<Value, String> {
+        let (env, _) = CandidSource::Text(DID).load().unwrap();
+        let ty = env.find_type(name).unwrap().clone();
+        to_idl(&env, &ty, &json, "").map(|idl| to_json(&idl))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let transfer = json!({"to": "aaaaa-aa", "amount": "340282366920938463463374607431768211456", "memo": "cafe"});
+        assert_eq!(round_trip("Transfer", transfer.clone()), Ok(transfer));
+        assert_eq!(
+            round_trip("Transfer", json!({"to": "aaaaa-aa", "amount": 5})),
+            Ok(json!({"to": "aaaaa-aa", "amount": 5, "memo": null}))
+        );
+
+        assert_eq!(
+            round_trip("Result", json!({"Ok": "18446744073709551615"})),
+            Ok(json!({"Ok": "18446744073709551615"}))
+        );
+        assert_eq!(
+            round_trip("Result", json!("Pending")),
+            Ok(json!({"Pending": null}))
+        );
+        assert_eq!(round_trip("Pair", json!(["a", -1])), Ok(json!(["a", -1])));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            round_trip("Transfer", json!({"amount": 1})),
+            Err("/to is required".to_string())
+        );
+        assert_eq!(
+            round_trip("Transfer", json!({"to": "aaaaa-aa", "amount": -1})),
+            Err("/amount must be nat".to_string())
+        );
+        assert_eq!(
+            round_trip("Pair", json!(["a", 300])),
+            Err("/1 must be int8".to_string())
+        );
+        assert!(round_trip("Result", json!({"Unknown": null})).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! Procedural macros for ic-rmcp.
+//!
+//! - [`macro@tool_router`] turns the `#[tool]` methods of an `impl` block into an
+//!   `ic_rmcp::ToolRouter` implementation
+//! - [`macro@tool_handler`] fills in `list_tools` and `call_tool` of an `ic_rmcp::Handler`
+//!   implementation from that router
+//!
+//! Use them through the re-exports in `ic_rmcp`.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Expr, ExprLit, FnArg, ImplItem, ImplItemFn,
+    ItemImpl, Lit, LitBool, LitStr, Meta, Token, Type,
+};
+
+/// Mark an async method of a [`macro@tool_router`] block as an MCP tool.
+///
+/// Accepted forms:
+/// - `async fn name(&self) -> Result<CallToolResult, Error>`
+/// - `async fn name(&self, args: T) -> Result<CallToolResult, Error>`
+/// - `async fn name(&self, context: Context, args: T) -> Result<CallToolResult, Error>`
+///
+/// The error type may also be `ic_rmcp::ToolError`, whose failures are returned as results with
+/// `isError` set.
+///
+/// `T` must implement `Deserialize` and `JsonSchema`; its schema becomes the tool's input schema.
+///
+/// Options: `name = "..."` (defaults to the method name) and `description = "..."` (defaults to
+/// the method's doc comment).
+///
+/// Annotations: `title = "..."` and the hints `read_only`, `destructive`, `idempotent` and
+/// `open_world`, either bare (`true`) or with a value such as `destructive = false`.
+///
+/// `query` lets `Server::handle_query` answer calls to the tool from certified responses,
+/// without an upgrade (see `Handler::query_tool`); the tool must not make inter-canister calls or
+/// HTTPS outcalls.
+#[proc_macro_attribute]
+pub fn tool(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    // Only meaningful inside `#[tool_router]`, which consumes the attribute.
+    item
+}
+
+/// Generate an `ic_rmcp::ToolRouter` implementation from the `#[tool]` methods of an `impl` block.
+#[proc_macro_attribute]
+pub fn tool_router(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(item as ItemImpl);
+    match expand_tool_router(&mut item) {
+        Ok(router) => quote!(#item #router).into(),
+        Err(err) => {
+            let err = err.to_compile_error();
+            quote!(#item #err).into()
+        }
+    }
+}
+
+/// Add `list_tools`, `call_tool` and `query_tool` to an `impl Handler for T` block, delegating to
+/// the `ic_rmcp::ToolRouter` implementation generated by [`macro@tool_router`].
+///
+/// Methods already present in the block are left untouched.
+#[proc_macro_attribute]
+pub fn tool_handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(item as ItemImpl);
+    let defined = |name: &str| {
+        item.items
+            .iter()
+            .any(|item| matches!(item, ImplItem::Fn(f) if f.sig.ident == name))
+    };
+
+    let mut methods = Vec::new();
+    if !defined("list_tools") {
+        methods.push(quote! {
+            async fn list_tools(
+                &self,
+                _: ::ic_rmcp::Context,
+                _: ::std::option::Option<::ic_rmcp::model::PaginatedRequestParam>,
+            ) -> ::std::result::Result<::ic_rmcp::model::ListToolsResult, ::ic_rmcp::Error> {
+                ::std::result::Result::Ok(::ic_rmcp::model::ListToolsResult {
+                    next_cursor: ::std::option::Option::None,
+                    tools: <Self as ::ic_rmcp::ToolRouter>::tools(),
+                })
+            }
+        });
+    }
+    if !defined("call_tool") {
+        methods.push(quote! {
+            async fn call_tool(
+                &self,
+                context: ::ic_rmcp::Context,
+                request: ::ic_rmcp::model::CallToolRequestParam,
+            ) -> ::std::result::Result<::ic_rmcp::model::CallToolResult, ::ic_rmcp::Error> {
+                ::ic_rmcp::ToolRouter::route(self, context, request).await
+            }
+        });
+    }
+
+    if !defined("query_tool") {
+        methods.push(quote! {
+            fn query_tool(&self, name: &str) -> bool {
+                <Self as ::ic_rmcp::ToolRouter>::is_query_tool(name)
+            }
+        });
+    }
+
+    for method in methods {
+        item.items.push(ImplItem::Verbatim(method));
+    }
+    quote!(#item).into()
+}
+
+#[derive(Default)]
+struct Annotations {
+    title: Option<String>,
+    read_only: Option<bool>,
+    destructive: Option<bool>,
+    idempotent: Option<bool>,
+    open_world: Option<bool>,
+}
+
+struct ToolMethod {
+    ident: syn::Ident,
+    name: String,
+    description: Option<String>,
+    annotations: Annotations,
+    query: bool,
+    takes_context: bool,
+    args: Option<Type>,
+}
+
+fn expand_tool_router(item: &mut ItemImpl) -> syn::Result<TokenStream2> {
+    let mut tools = Vec::new();
+    for method in item.items.iter_mut().filter_map(|item| match item {
+        ImplItem::Fn(method) => Some(method),
+        _ => None,
+    }) {
+        let Some(index) = method.attrs.iter().position(|a| a.path().is_ident("tool")) else {
+            continue;
+        };
+        let attr = method.attrs.remove(index);
+        tools.push(parse_tool(&attr, method)?);
+    }
+
+    let self_ty = &item.self_ty;
+    let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+
+    let definitions = tools.iter().map(|tool| {
+        let name = &tool.name;
+        let description = match &tool.description {
+            Some(description) => quote!(::std::option::Option::Some(#description.into())),
+            None => quote!(::std::option::Option::None),
+        };
+        let schema = match &tool.args {
+            Some(args) => quote!(::ic_rmcp::schema_for_type::<#args>()),
+            None => quote!(::ic_rmcp::schema_for_type::<::ic_rmcp::model::EmptyObject>()),
+        };
+        let annotations = annotations(&tool.annotations);
+        quote! {
+            ::ic_rmcp::model::Tool {
+                name: #name.into(),
+                title: None,
+                description: #description,
+                input_schema: ::std::sync::Arc::new(#schema),
+                output_schema: None,
+                annotations: #annotations,
+                icons: None,
+            }
+        }
+    });
+
+    let routes = tools.iter().map(|tool| {
+        let name = &tool.name;
+        let ident = &tool.ident;
+        let mut call_args = Vec::new();
+        if tool.takes_context {
+            call_args.push(quote!(context));
+        }
+        let parse = tool.args.as_ref().map(|args| {
+            call_args.push(quote!(args));
+            quote! {
+                let args = ::ic_rmcp::parse_arguments::<#args>(#name, request.arguments)?;
+            }
+        });
+        quote! {
+            #name => {
+                #parse
+                ::ic_rmcp::IntoToolResult::into_tool_result(self.#ident(#(#call_args),*).await)
+            }
+        }
+    });
+
+    let query_tools = tools
+        .iter()
+        .filter(|tool| tool.query)
+        .map(|tool| &tool.name);
+
+    Ok(quote! {
+        impl #impl_generics ::ic_rmcp::ToolRouter for #self_ty #where_clause {
+            fn tools() -> ::std::vec::Vec<::ic_rmcp::model::Tool> {
+                ::std::vec![#(#definitions),*]
+            }
+
+            fn is_query_tool(name: &str) -> bool {
+                [#(#query_tools),*].contains(&name)
+            }
+
+            #[allow(unused_variables)]
+            async fn route(
+                &self,
+                context: ::ic_rmcp::Context,
+                request: ::ic_rmcp::model::CallToolRequestParam,
+            ) -> ::std::result::Result<::ic_rmcp::model::CallToolResult, ::ic_rmcp::Error> {
+                match request.name.as_ref() {
+                    #(#routes)*
+                    _ => ::std::result::Result::Err(::ic_rmcp::tool_not_found(&request.name)),
+                }
+            }
+        }
+    })
+}
+
+fn annotations(annotations: &Annotations) -> TokenStream2 {
+    let Annotations {
+        title,
+        read_only,
+        destructive,
+        idempotent,
+        open_world,
+    } = annotations;
+    if title.is_none()
+        && read_only.is_none()
+        && destructive.is_none()
+        && idempotent.is_none()
+        && open_world.is_none()
+    {
+        return quote!(::std::option::Option::None);
+    }
+
+    let option = |value: Option<TokenStream2>| match value {
+        Some(value) => quote!(::std::option::Option::Some(#value)),
+        None => quote!(::std::option::Option::None),
+    };
+    let title = option(title.as_ref().map(|title| quote!(#title.to_string())));
+    let [read_only, destructive, idempotent, open_world] =
+        [read_only, destructive, idempotent, open_world]
+            .map(|hint| option(hint.map(|h| quote!(#h))));
+    quote! {
+        ::std::option::Option::Some(::ic_rmcp::model::ToolAnnotations {
+            title: #title,
+            read_only_hint: #read_only,
+            destructive_hint: #destructive,
+            idempotent_hint: #idempotent,
+            open_world_hint: #open_world,
+        })
+    }
+}
+
+fn parse_tool(attr: &Attribute, method: &ImplItemFn) -> syn::Result<ToolMethod> {
+    let mut name = None;
+    let mut description = None;
+    let mut annotations = Annotations::default();
+    let mut query = false;
+    if let Meta::List(_) = attr.meta {
+        attr.parse_nested_meta(|meta| {
+            // Hints may be given bare, as in `#[tool(read_only)]`.
+            let hint = || -> syn::Result<bool> {
+                match meta.input.peek(Token![=]) {
+                    true => Ok(meta.value()?.parse::<LitBool>()?.value),
+                    false => Ok(true),
+                }
+            };
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("description") {
+                description = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("title") {
+                annotations.title = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("read_only") {
+                annotations.read_only = Some(hint()?);
+            } else if meta.path.is_ident("destructive") {
+                annotations.destructive = Some(hint()?);
+            } else if meta.path.is_ident("idempotent") {
+                annotations.idempotent = Some(hint()?);
+            } else if meta.path.is_ident("open_world") {
+                annotations.open_world = Some(hint()?);
+            } else if meta.path.is_ident("query") {
+                query = hint()?;
+            } else {
+                return Err(meta.error(
+                    "expected `name`, `description`, `title`, `read_only`, `destructive`, `idempotent`, `open_world` or `query`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    if method.sig.asyncness.is_none() {
+        return Err(syn::Error::new(
+            method.sig.fn_token.span(),
+            "#[tool] methods must be async",
+        ));
+    }
+    if !matches!(method.sig.inputs.first(), Some(FnArg::Receiver(_))) {
+        return Err(syn::Error::new(
+            method.sig.span(),
+            "#[tool] methods must take `&self`",
+        ));
+    }
+
+    let mut takes_context = false;
+    let mut args = None;
+    for (position, input) in method.sig.inputs.iter().skip(1).enumerate() {
+        let FnArg::Typed(input) = input else {
+            continue;
+        };
+        if position == 0 && is_context(&input.ty) {
+            takes_context = true;
+        } else if args.is_none() {
+            args = Some((*input.ty).clone());
+        } else {
+            return Err(syn::Error::new(
+                input.span(),
+                "#[tool] methods take at most a `Context` and one arguments type",
+            ));
+        }
+    }
+
+    Ok(ToolMethod {
+        ident: method.sig.ident.clone(),
+        name: name.unwrap_or_else(|| method.sig.ident.to_string()),
+        description: description.or_else(|| doc_comment(&method.attrs)),
+        annotations,
+        query,
+        takes_context,
+        args,
+    })
+}
+
+fn is_context(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Context"),
+        _ => false,
+    }
+}
+
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
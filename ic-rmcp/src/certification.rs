@@ -0,0 +1,533 @@
+//! Certified responses for the `http_request` query endpoint.
+//!
+//! A query call is answered by a single replica, so the HTTP gateway only trusts its response
+//! if it is certified: the canister commits to a hash tree of responses in its certified data,
+//! and the response carries an `IC-Certificate` header proving it is part of that tree.
+//!
+//! Call [`init_certification`] from your canister's `init` and `post_upgrade` hooks to enable
+//! it. Once enabled:
+//! - `http_request_update` certifies its responses to the requests a query may answer:
+//!   `initialize` (unless sessions are enabled), `ping`, `tools/list`, calls to tools opted in
+//!   with [`Handler::query_tool`], CORS preflights and the OAuth metadata document.
+//!   [`init_certification`] certifies the `initialize` and `tools/list` responses to a few
+//!   typical requests up front
+//! - `http_request` serves those responses with their certificate, and upgrades every request
+//!   whose response is not certified, since the gateway would reject it
+//!
+//! Each response is certified together with the request it answers: its method, path, body
+//! and the headers the response may depend on (`Authorization`, `Mcp-Session-Id`,
+//! `MCP-Protocol-Version`, `Origin` and the CORS preflight headers). A certificate vouches for
+//! nothing else, so a response is only served from the query for a request identical to the one
+//! an update call answered, JSON-RPC `id` included.
+//!
+//! A query only serves a certified response that equals the one it computes, so a stale one is
+//! upgraded and certified again. Call [`invalidate_certified_responses`] from the update call
+//! that changes the tools or server info to drop them right away;
+//! [`ToolRegistry`](crate::ToolRegistry) does so itself.
+use crate::handler::{Handler, Service};
+use crate::session::sessions_enabled;
+use crate::state::CERTIFICATION;
+use ic_cdk::eprintln;
+use ic_http_certification::{
+    utils::add_v2_certificate_header, DefaultCelBuilder, DefaultFullCelExpression,
+    DefaultResponseCertification, HttpCertification, HttpCertificationPath, HttpCertificationTree,
+    HttpCertificationTreeEntry, HttpRequest, HttpResponse, Method,
+    CERTIFICATE_EXPRESSION_HEADER_NAME,
+};
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context as TaskContext, Poll, Waker};
+
+/// How many responses are kept certified; the oldest is dropped first.
+const MAX_RESPONSES: usize = 64;
+
+/// Protocol versions whose `initialize` and `tools/list` responses are certified up front.
+const VERSIONS: [&str; 2] = ["2025-03-26", "2025-06-18"];
+
+/// Request headers certified along with the method, path and body of a request.
+const REQUEST_HEADERS: [&str; 6] = [
+    "Authorization",
+    "Mcp-Session-Id",
+    "MCP-Protocol-Version",
+    "Origin",
+    "Access-Control-Request-Method",
+    "Access-Control-Request-Headers",
+];
+
+/// Enable certified query responses.
+///
+/// Certifies the `initialize` (unless sessions are enabled, so call
+/// [`init_sessions`](crate::init_sessions) first) and `tools/list` responses of `server` to
+/// a few typical requests on each of its paths, when its handlers answer them without awaiting.
+pub fn init_certification(server: &impl Handler) {
+    let mut responses = CertifiedResponses::default();
+    for path in server.server_config().paths {
+        for req in prepopulated(&path) {
+            if let Some(res) = now_or_never(server.raw_handle(None, &req)) {
+                responses.certify(&req, &server.with_cors(&req, res));
+            }
+        }
+    }
+    set_certified_data(&responses.tree.root_hash());
+    CERTIFICATION.with_borrow_mut(|certification| *certification = Some(responses));
+}
+
+/// Drop all certified responses, so that they are computed and certified again by the next
+/// update calls. Must be called from an update call (or `init`/`post_upgrade`).
+pub fn invalidate_certified_responses() {
+    CERTIFICATION.with_borrow_mut(|certification| {
+        if let Some(responses) = certification {
+            responses.clear();
+            set_certified_data(&responses.tree.root_hash());
+        }
+    });
+}
+
+pub(crate) fn certification_enabled() -> bool {
+    CERTIFICATION.with_borrow(|certification| certification.is_some())
+}
+
+/// Certify `res` as the response to `req`, if certification is enabled and `res` is a success
+/// that does not start a session. The caller decides whether `req` may be served from a query.
+pub(crate) fn certify(req: &HttpRequest, res: &HttpResponse) {
+    CERTIFICATION.with_borrow_mut(|certification| {
+        if let Some(responses) = certification {
+            if responses.certify(req, res) {
+                set_certified_data(&responses.tree.root_hash());
+            }
+        }
+    });
+}
+
+/// The certified response to `req`, with its certificate, if it equals `res`.
+pub(crate) fn certified(req: &HttpRequest, res: &HttpResponse) -> Option<HttpResponse<'static>> {
+    let certificate = data_certificate()?;
+    CERTIFICATION
+        .with_borrow(|certification| certification.as_ref()?.certified(req, res, &certificate))
+}
+
+/// Requests on `path` whose responses [`init_certification`] certifies.
+fn prepopulated(path: &str) -> Vec<HttpRequest<'static>> {
+    let post = |version: Option<&str>, body: String| {
+        HttpRequest::builder()
+            .with_method(Method::POST)
+            .with_url(path)
+            .with_headers(
+                version
+                    .map(|version| ("MCP-Protocol-Version".to_string(), version.to_string()))
+                    .into_iter()
+                    .collect(),
+            )
+            .with_body(body.into_bytes())
+            .build()
+    };
+
+    let mut requests = Vec::new();
+    if !sessions_enabled() {
+        for version in VERSIONS {
+            requests.push(post(
+                None,
+                format!(
+                    r#"{{"jsonrpc":"2.0","id":0,"method":"initialize","params":{{"protocolVersion":"{version}","capabilities":{{}},"clientInfo":{{"name":"","version":""}}}}}}"#
+                ),
+            ));
+        }
+    }
+    for version in [None, Some(VERSIONS[0]), Some(VERSIONS[1])] {
+        requests.push(post(
+            version,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#.to_string(),
+        ));
+    }
+    requests
+}
+
+/// The output of `future`, if it completes without waiting, as handlers that only read their
+/// own state do.
+fn now_or_never<F: Future>(future: F) -> Option<F::Output> {
+    match pin!(future).poll(&mut TaskContext::from_waker(Waker::noop())) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
+
+/// The parts of a request that its certification covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Key {
+    method: String,
+    path: String,
+    /// Values of the [`REQUEST_HEADERS`], in order.
+    headers: Vec<Option<String>>,
+    body: Vec<u8>,
+}
+
+impl Key {
+    fn new(req: &HttpRequest) -> Option<Self> {
+        Some(Key {
+            method: req.method().to_string(),
+            path: req.get_path().ok()?,
+            headers: REQUEST_HEADERS
+                .iter()
+                .map(|name| {
+                    req.headers()
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                        .map(|(_, value)| value.clone())
+                })
+                .collect(),
+            body: req.body().to_vec(),
+        })
+    }
+}
+
+struct Certified {
+    key: Key,
+    /// The response as computed, without the certificate expression header.
+    response: HttpResponse<'static>,
+    entry: HttpCertificationTreeEntry<'static>,
+}
+
+/// The certified responses and the tree committing to them.
+#[derive(Default)]
+pub(crate) struct CertifiedResponses {
+    tree: HttpCertificationTree,
+    responses: Vec<Certified>,
+}
+
+impl CertifiedResponses {
+    /// Certify `res` for `req`, replacing an earlier response to the same request. Returns
+    /// whether the tree changed.
+    fn certify(&mut self, req: &HttpRequest, res: &HttpResponse) -> bool {
+        let Some(key) = Key::new(req) else {
+            return false;
+        };
+        // Responses starting a session are specific to one client.
+        let session = res
+            .headers()
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("Mcp-Session-Id"));
+        if res.upgrade() == Some(true) || !res.status_code().is_success() || session {
+            return false;
+        }
+
+        if let Some(index) = self.responses.iter().position(|c| c.key == key) {
+            if self.responses[index].response == *res {
+                return false;
+            }
+            self.remove(index);
+        }
+
+        let response = owned(res);
+        let certification =
+            match HttpCertification::full(&expression(), req, &with_expression(&response), None) {
+                Ok(certification) => certification,
+                Err(err) => {
+                    eprintln!("Certify response: {}", err);
+                    return false;
+                }
+            };
+        if self.responses.len() >= MAX_RESPONSES {
+            self.remove(0);
+        }
+        let entry = HttpCertificationTreeEntry::new(
+            HttpCertificationPath::exact(key.path.clone()),
+            certification,
+        );
+        self.tree.insert(&entry);
+        self.responses.push(Certified {
+            key,
+            response,
+            entry,
+        });
+        true
+    }
+
+    /// The response certified for `req`, with a certificate proving it, if it equals `res`.
+    fn certified(
+        &self,
+        req: &HttpRequest,
+        res: &HttpResponse,
+        certificate: &[u8],
+    ) -> Option<HttpResponse<'static>> {
+        let key = Key::new(req)?;
+        let certified = self.responses.iter().find(|c| c.key == key)?;
+        if certified.response != *res {
+            return None;
+        }
+
+        let witness = match self.tree.witness(&certified.entry, req.url()) {
+            Ok(witness) => witness,
+            Err(err) => {
+                eprintln!("Witness response: {}", err);
+                return None;
+            }
+        };
+        let mut response = with_expression(&certified.response);
+        add_v2_certificate_header(
+            certificate,
+            &mut response,
+            &witness,
+            &HttpCertificationPath::exact(key.path).to_expr_path(),
+        );
+        Some(response)
+    }
+
+    fn remove(&mut self, index: usize) {
+        let certified = self.responses.remove(index);
+        self.tree.delete(&certified.entry);
+    }
+
+    fn clear(&mut self) {
+        while !self.responses.is_empty() {
+            self.remove(0);
+        }
+    }
+}
+
+/// The certification expression of certified responses: the request's method, body and
+/// [`REQUEST_HEADERS`], and the whole response.
+fn expression() -> DefaultFullCelExpression<'static> {
+    DefaultCelBuilder::full_certification()
+        .with_request_headers(REQUEST_HEADERS.to_vec())
+        .with_request_query_parameters(vec![])
+        .with_response_certification(DefaultResponseCertification::response_header_exclusions(
+            vec![],
+        ))
+        .build()
+}
+
+/// A copy of `res` that outlives its request.
+fn owned(res: &HttpResponse) -> HttpResponse<'static> {
+    HttpResponse::builder()
+        .with_status_code(res.status_code())
+        .with_headers(res.headers().to_vec())
+        .with_body(res.body().to_vec())
+        .build()
+}
+
+/// `res` with the header naming its certification expression, which is certified too.
+fn with_expression(res: &HttpResponse) -> HttpResponse<'static> {
+    let mut res = owned(res);
+    res.add_header((
+        CERTIFICATE_EXPRESSION_HEADER_NAME.to_string(),
+        expression().to_string(),
+    ));
+    res
+}
+
+#[cfg(target_arch = "wasm32")]
+fn set_certified_data(hash: &[u8]) {
+    ic_cdk::api::certified_data_set(hash);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn data_certificate() -> Option<Vec<u8>> {
+    ic_cdk::api::data_certificate()
+}
+
+// Native builds (unit tests) have no system API; certify against an empty certificate.
+#[cfg(not(target_arch = "wasm32"))]
+fn set_certified_data(_: &[u8]) {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn data_certificate() -> Option<Vec<u8>> {
+    Some(vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_http_certification::{Method, StatusCode};
+
+    fn request(body: &str) -> HttpRequest<'static> {
+        HttpRequest::builder()
+            .with_method(Method::POST)
+            .with_url("/mcp")
+            .with_headers(vec![(
+                "MCP-Protocol-Version".to_string(),
+                "2025-06-18".to_string(),
+            )])
+            .with_body(body.as_bytes().to_vec())
+            .build()
+    }
+
+    fn response(body: &str) -> HttpResponse<'static> {
+        HttpResponse::builder()
+            .with_status_code(StatusCode::OK)
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string(),
+            )])
+            .with_body(body.as_bytes().to_vec())
+            .build()
+    }
+
+    fn has_certificate(res: &HttpResponse) -> bool {
+        res.headers()
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("IC-Certificate"))
+    }
+
+    /// Whether a gateway could verify `res` as the response to `req` against the tree, as a
+    /// certified response or as exempt from certification.
+    fn verifies(responses: &CertifiedResponses, req: &HttpRequest, res: &HttpResponse) -> bool {
+        let full = HttpCertification::full(&expression(), req, &with_expression(res), None);
+        [HttpCertification::skip(), full.unwrap()]
+            .into_iter()
+            .any(|certification| {
+                let entry = HttpCertificationTreeEntry::new(
+                    HttpCertificationPath::exact(req.get_path().unwrap()),
+                    certification,
+                );
+                responses.tree.witness(&entry, req.url()).is_ok()
+            })
+    }
+
+    #[test]
+    fn test_certified_responses() {
+        let mut responses = CertifiedResponses::default();
+        let list = request(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#);
+        let tools = response(r#"{"jsonrpc":"2.0","id":1,"result":{"tools":[]}}"#);
+
+        assert_eq!(responses.certified(&list, &tools, b""), None);
+        assert!(responses.certify(&list, &tools));
+        assert!(!responses.certify(&list, &tools));
+
+        let served = responses.certified(&list, &tools, b"").unwrap();
+        assert_eq!(served.body(), tools.body());
+        assert!(has_certificate(&served));
+        assert!(served
+            .headers()
+            .iter()
+            .any(|(key, _)| key == CERTIFICATE_EXPRESSION_HEADER_NAME));
+        assert!(verifies(&responses, &list, &tools));
+
+        // Only the exact request is covered: another id, body or certified header is not.
+        let other_id = request(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#);
+        assert_eq!(responses.certified(&other_id, &tools, b""), None);
+        let with_meta =
+            request(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list","params":{"_meta":{}}}"#);
+        assert_eq!(responses.certified(&with_meta, &tools, b""), None);
+        for (name, value) in [
+            ("Authorization", "Bearer token"),
+            ("Origin", "https://example.com"),
+            ("MCP-Protocol-Version", "2025-03-26"),
+        ] {
+            let mut headers: Vec<_> = list
+                .headers()
+                .iter()
+                .filter(|(key, _)| !key.eq_ignore_ascii_case(name))
+                .cloned()
+                .collect();
+            headers.push((name.to_string(), value.to_string()));
+            let other = HttpRequest::builder()
+                .with_method(Method::POST)
+                .with_url("/mcp")
+                .with_headers(headers)
+                .with_body(list.body().to_vec())
+                .build();
+            assert_eq!(responses.certified(&other, &tools, b""), None, "{name}");
+        }
+        let get = HttpRequest::get("/mcp").build();
+        assert_eq!(responses.certified(&get, &tools, b""), None);
+
+        // A response that changed since is not covered, until certified again.
+        let changed = response(r#"{"jsonrpc":"2.0","id":1,"result":{"tools":[{}]}}"#);
+        assert_eq!(responses.certified(&list, &changed, b""), None);
+        assert!(responses.certify(&list, &changed));
+        assert_eq!(responses.responses.len(), 1);
+        assert!(responses.certified(&list, &changed, b"").is_some());
+        assert_eq!(responses.certified(&list, &tools, b""), None);
+        assert!(!verifies(&responses, &list, &tools));
+
+        responses.clear();
+        assert_eq!(responses.certified(&list, &changed, b""), None);
+    }
+
+    #[test]
+    fn test_certify_rejects() {
+        let mut responses = CertifiedResponses::default();
+        let init = request(r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#);
+
+        let mut session = response(r#"{"jsonrpc":"2.0","id":0,"result":{}}"#);
+        session.add_header(("Mcp-Session-Id".to_string(), "abc".to_string()));
+        assert!(!responses.certify(&init, &session));
+
+        let not_found = HttpResponse::builder()
+            .with_status_code(StatusCode::NOT_FOUND)
+            .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+            .build();
+        assert!(!responses.certify(&init, &not_found));
+
+        let upgrade = HttpResponse::builder().with_upgrade(true).build();
+        assert!(!responses.certify(&init, &upgrade));
+
+        for id in 0..MAX_RESPONSES + 1 {
+            let req = request(&format!(
+                r#"{{"jsonrpc":"2.0","id":{id},"method":"tools/list"}}"#
+            ));
+            let res = response(&format!(r#"{{"jsonrpc":"2.0","id":{id},"result":{{}}}}"#));
+            assert!(responses.certify(&req, &res));
+        }
+        assert_eq!(responses.responses.len(), MAX_RESPONSES);
+        assert_eq!(
+            responses.certified(
+                &request(r#"{"jsonrpc":"2.0","id":0,"method":"tools/list"}"#),
+                &response(r#"{"jsonrpc":"2.0","id":0,"result":{}}"#),
+                b""
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_init_certification() {
+        use crate::model::{JsonObject, ListToolsResult, PaginatedRequestParam, Tool};
+        use crate::{Context, Error, Server};
+        use futures::executor::block_on;
+
+        struct A;
+        impl Handler for A {
+            async fn list_tools(
+                &self,
+                _: Context,
+                _: Option<PaginatedRequestParam>,
+            ) -> Result<ListToolsResult, Error> {
+                Ok(ListToolsResult {
+                    next_cursor: None,
+                    tools: vec![Tool::new("a", "A tool", JsonObject::new())],
+                })
+            }
+        }
+
+        init_certification(&A);
+        let query = |req: &HttpRequest| block_on(A.handle_query(req, |_| true));
+
+        // Served from the query before any update call.
+        let list = request(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#);
+        let res = query(&list);
+        assert_eq!(res.upgrade(), None);
+        assert!(has_certificate(&res));
+        let computed = owned(&block_on(A.handle(&list, |_| true)));
+        assert_eq!(res.body(), computed.body());
+
+        // Nothing else on the path is covered, tools/list bodies included.
+        let forged = response(r#"{"jsonrpc":"2.0","id":1,"result":{"tools":[]}}"#);
+        CERTIFICATION.with_borrow(|certification| {
+            let responses = certification.as_ref().unwrap();
+            assert!(verifies(responses, &list, &computed));
+            assert!(!verifies(responses, &list, &forged));
+            let call =
+                request(r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"a"}}"#);
+            assert!(!verifies(responses, &call, &computed));
+            let get = HttpRequest::get("/mcp").build();
+            assert!(!verifies(responses, &get, &computed));
+        });
+
+        // Other requests are upgraded until an update call has certified their response.
+        let other = request(r#"{"jsonrpc":"2.0","id":"x","method":"tools/list"}"#);
+        assert_eq!(query(&other).upgrade(), Some(true));
+        block_on(A.handle(&other, |_| true));
+        assert!(has_certificate(&query(&other)));
+    }
+}
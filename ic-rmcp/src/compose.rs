@@ -0,0 +1,908 @@
+//! Serving several [`Handler`]s from one canister.
+//!
+//! ```ignore
+//! let server = Compose::new()
+//!     .mount("ledger", LedgerTools)
+//!     .mount("admin", AdminTools);
+//! ```
+//!
+//! Tools and prompts of each mounted handler are exposed as `{prefix}_{name}`, so `transfer` from
+//! `LedgerTools` becomes `ledger_transfer`. Resources keep their URIs; `resources/read` is offered
+//! to each handler in mount order, with [`Context::resource_template`] matched against that
+//! handler's own templates. Capabilities and instructions are merged, and list cursors
+//! walk the handlers one after the other.
+//!
+//! Certified query responses (see [`init_certification`](crate::init_certification)) are kept per
+//! canister, not per handler: pass the `Compose` to `init_certification`, and a mounted
+//! [`ToolRegistry`](crate::ToolRegistry) whose tools change through a clone invalidates the
+//! responses of the whole composition. Other handlers that change their tools or info must call
+//! [`invalidate_certified_responses`](crate::invalidate_certified_responses) themselves.
+use crate::handler::{Context, Handler, Service};
+use crate::{model::*, tool_not_found, Error, ServerConfig};
+use std::future::Future;
+use std::pin::Pin;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Object-safe view of a [`Handler`], so that handlers of different types can be mounted together.
+trait DynHandler {
+    fn info(&self, context: Context) -> ServerInfo;
+    fn query_tool(&self, name: &str) -> bool;
+    fn completions(&self) -> bool;
+    fn call_tool(
+        &self,
+        context: Context,
+        request: CallToolRequestParam,
+    ) -> BoxFuture<'_, Result<CallToolResult, Error>>;
+    fn list_tools(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListToolsResult, Error>>;
+    fn list_resources(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListResourcesResult, Error>>;
+    fn list_resource_templates(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListResourceTemplatesResult, Error>>;
+    fn read_resource(
+        &self,
+        context: Context,
+        request: ReadResourceRequestParam,
+    ) -> BoxFuture<'_, Result<ReadResourceResult, Error>>;
+    fn list_prompts(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListPromptsResult, Error>>;
+    fn get_prompt(
+        &self,
+        context: Context,
+        request: GetPromptRequestParam,
+    ) -> BoxFuture<'_, Result<GetPromptResult, Error>>;
+    fn complete(
+        &self,
+        context: Context,
+        request: CompleteRequestParam,
+    ) -> BoxFuture<'_, Result<CompleteResult, Error>>;
+}
+
+impl<H: Handler> DynHandler for H {
+    fn info(&self, context: Context) -> ServerInfo {
+        Handler::get_info(self, context)
+    }
+    fn query_tool(&self, name: &str) -> bool {
+        Handler::query_tool(self, name)
+    }
+    fn completions(&self) -> bool {
+        Handler::completions(self)
+    }
+    fn call_tool(
+        &self,
+        context: Context,
+        request: CallToolRequestParam,
+    ) -> BoxFuture<'_, Result<CallToolResult, Error>> {
+        Box::pin(async move {
+            // Each handler keeps its own `validate_arguments` choice.
+            self.check_tool_arguments(context.clone(), &request).await?;
+            Handler::call_tool(self, context, request).await
+        })
+    }
+    fn list_tools(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListToolsResult, Error>> {
+        Box::pin(Handler::list_tools(
+            self,
+            context,
+            Some(PaginatedRequestParam { cursor }),
+        ))
+    }
+    fn list_resources(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListResourcesResult, Error>> {
+        Box::pin(Handler::list_resources(
+            self,
+            context,
+            Some(PaginatedRequestParam { cursor }),
+        ))
+    }
+    fn list_resource_templates(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListResourceTemplatesResult, Error>> {
+        Box::pin(Handler::list_resource_templates(
+            self,
+            context,
+            Some(PaginatedRequestParam { cursor }),
+        ))
+    }
+    fn read_resource(
+        &self,
+        context: Context,
+        request: ReadResourceRequestParam,
+    ) -> BoxFuture<'_, Result<ReadResourceResult, Error>> {
+        Box::pin(async move {
+            // Match against this handler's own templates rather than the merged list, whose
+            // first match may belong to another handler.
+            let context = Context {
+                resource_template: None,
+                uri_params: Default::default(),
+                ..context
+            };
+            let context = self.match_template(context, &request.uri).await;
+            Handler::read_resource(self, context, request).await
+        })
+    }
+    fn list_prompts(
+        &self,
+        context: Context,
+        cursor: Option<String>,
+    ) -> BoxFuture<'_, Result<ListPromptsResult, Error>> {
+        Box::pin(Handler::list_prompts(
+            self,
+            context,
+            Some(PaginatedRequestParam { cursor }),
+        ))
+    }
+    fn get_prompt(
+        &self,
+        context: Context,
+        request: GetPromptRequestParam,
+    ) -> BoxFuture<'_, Result<GetPromptResult, Error>> {
+        Box::pin(Handler::get_prompt(self, context, request))
+    }
+    fn complete(
+        &self,
+        context: Context,
+        request: CompleteRequestParam,
+    ) -> BoxFuture<'_, Result<CompleteResult, Error>> {
+        Box::pin(Handler::complete(self, context, request))
+    }
+}
+
+struct Mount {
+    prefix: String,
+    handler: Box<dyn DynHandler>,
+}
+
+impl Mount {
+    fn qualify(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, name)
+    }
+}
+
+/// A [`Handler`] combining several mounted handlers under name prefixes.
+///
+/// Tool and prompt names are namespaced as `{prefix}_{name}`. When prefixes overlap (e.g. `a`
+/// and `a_b`), the longest matching prefix wins.
+#[derive(Default)]
+pub struct Compose {
+    info: ServerInfo,
+    config: ServerConfig,
+    destructive_scope: Option<String>,
+    mounts: Vec<Mount>,
+}
+
+impl Compose {
+    /// Create a server with no mounted handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the `server_info`, `instructions` and base capabilities returned on `initialize`.
+    ///
+    /// Capabilities and instructions of mounted handlers are merged into these.
+    pub fn with_info(mut self, info: ServerInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Set the paths the composed server is mounted at.
+    pub fn with_config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Require `scope` to call destructive tools of any mounted handler; see
+    /// [`Handler::destructive_scope`].
+    pub fn with_destructive_scope(mut self, scope: impl Into<String>) -> Self {
+        self.destructive_scope = Some(scope.into());
+        self
+    }
+
+    /// Mount `handler` with its tools and prompts exposed as `{prefix}_{name}`.
+    pub fn mount(mut self, prefix: impl Into<String>, handler: impl Handler + 'static) -> Self {
+        self.mounts.push(Mount {
+            prefix: prefix.into(),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// The mount owning `name`, with the prefix stripped.
+    fn route<'a>(&self, name: &'a str) -> Option<(&Mount, &'a str)> {
+        self.mounts
+            .iter()
+            .filter_map(|mount| {
+                name.strip_prefix(mount.prefix.as_str())
+                    .and_then(|rest| rest.strip_prefix('_'))
+                    .map(|rest| (mount, rest))
+            })
+            .max_by_key(|(mount, _)| mount.prefix.len())
+    }
+
+    /// Fetch one page from the mount addressed by `cursor`, skipping mounts with nothing to list.
+    ///
+    /// Cursors have the form `{mount index}:{cursor of that mount}`.
+    async fn paginate<'a, T>(
+        &'a self,
+        cursor: Option<String>,
+        list: impl Fn(
+            &'a Mount,
+            Option<String>,
+        ) -> BoxFuture<'a, Result<(Vec<T>, Option<String>), Error>>,
+    ) -> Result<(Vec<T>, Option<String>), Error> {
+        let (mut index, mut cursor) = match cursor {
+            None => (0, None),
+            Some(cursor) => {
+                let (index, inner) = cursor
+                    .split_once(':')
+                    .and_then(|(index, inner)| Some((index.parse::<usize>().ok()?, inner)))
+                    .filter(|(index, _)| *index < self.mounts.len())
+                    .ok_or_else(|| Error::invalid_params("invalid cursor", None))?;
+                (index, (!inner.is_empty()).then(|| inner.to_string()))
+            }
+        };
+
+        while let Some(mount) = self.mounts.get(index) {
+            let (items, next) = list(mount, cursor.take()).await?;
+            if let Some(next) = next {
+                return Ok((items, Some(format!("{index}:{next}"))));
+            }
+            index += 1;
+            if !items.is_empty() {
+                let next = (index < self.mounts.len()).then(|| format!("{index}:"));
+                return Ok((items, next));
+            }
+        }
+        Ok((Vec::new(), None))
+    }
+
+    /// Offer a request to each mount in order until one of them recognizes it.
+    async fn first<'a, T>(
+        &'a self,
+        attempt: impl Fn(&'a Mount) -> BoxFuture<'a, Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let mut first_error = None;
+        for mount in &self.mounts {
+            match attempt(mount).await {
+                Err(err)
+                    if err.code == ErrorCode::METHOD_NOT_FOUND
+                        || err.code == ErrorCode::RESOURCE_NOT_FOUND =>
+                {
+                    first_error.get_or_insert(err);
+                }
+                result => return result,
+            }
+        }
+        Err(first_error
+            .unwrap_or_else(|| Error::new(ErrorCode::METHOD_NOT_FOUND, "Method not found", None)))
+    }
+}
+
+fn merge_capabilities(into: &mut ServerCapabilities, from: ServerCapabilities) {
+    if let Some(experimental) = from.experimental {
+        into.experimental
+            .get_or_insert_with(Default::default)
+            .extend(experimental);
+    }
+    into.logging = into.logging.take().or(from.logging);
+    into.completions = into.completions.take().or(from.completions);
+    if let Some(prompts) = from.prompts {
+        let into = into.prompts.get_or_insert_with(Default::default);
+        into.list_changed = into.list_changed.max(prompts.list_changed);
+    }
+    if let Some(resources) = from.resources {
+        let into = into.resources.get_or_insert_with(Default::default);
+        into.subscribe = into.subscribe.max(resources.subscribe);
+        into.list_changed = into.list_changed.max(resources.list_changed);
+    }
+    if let Some(tools) = from.tools {
+        let into = into.tools.get_or_insert_with(Default::default);
+        into.list_changed = into.list_changed.max(tools.list_changed);
+    }
+}
+
+impl Handler for Compose {
+    fn server_config(&self) -> ServerConfig {
+        self.config.clone()
+    }
+
+    fn destructive_scope(&self) -> Option<&str> {
+        self.destructive_scope.as_deref()
+    }
+
+    fn query_tool(&self, name: &str) -> bool {
+        self.route(name)
+            .is_some_and(|(mount, name)| mount.handler.query_tool(name))
+    }
+
+    fn completions(&self) -> bool {
+        self.mounts.iter().any(|mount| mount.handler.completions())
+    }
+
+    fn get_info(&self, context: Context) -> ServerInfo {
+        let mut info = self.info.clone();
+        let mut instructions: Vec<String> = info.instructions.take().into_iter().collect();
+        for mount in &self.mounts {
+            let child = mount.handler.info(context.clone());
+            merge_capabilities(&mut info.capabilities, child.capabilities);
+            if let Some(text) = child.instructions {
+                instructions.push(format!("{}: {}", mount.prefix, text));
+            }
+        }
+        info.instructions = (!instructions.is_empty()).then(|| instructions.join("\n\n"));
+        info
+    }
+
+    async fn list_tools(
+        &self,
+        context: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> Result<ListToolsResult, Error> {
+        let cursor = request.and_then(|r| r.cursor);
+        let (tools, next_cursor) = self
+            .paginate(cursor, |mount, cursor| {
+                let context = context.clone();
+                Box::pin(async move {
+                    let page = mount.handler.list_tools(context, cursor).await?;
+                    let tools = page
+                        .tools
+                        .into_iter()
+                        .map(|tool| Tool {
+                            name: mount.qualify(&tool.name).into(),
+                            ..tool
+                        })
+                        .collect();
+                    Ok((tools, page.next_cursor))
+                })
+            })
+            .await?;
+        Ok(ListToolsResult { next_cursor, tools })
+    }
+
+    async fn call_tool(
+        &self,
+        context: Context,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, Error> {
+        match self.route(&request.name) {
+            Some((mount, name)) => {
+                let request = CallToolRequestParam {
+                    name: name.to_string().into(),
+                    arguments: request.arguments,
+                };
+                mount.handler.call_tool(context, request).await
+            }
+            None => Err(tool_not_found(&request.name)),
+        }
+    }
+
+    async fn list_resources(
+        &self,
+        context: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> Result<ListResourcesResult, Error> {
+        let cursor = request.and_then(|r| r.cursor);
+        let (resources, next_cursor) = self
+            .paginate(cursor, |mount, cursor| {
+                let context = context.clone();
+                Box::pin(async move {
+                    let page = mount.handler.list_resources(context, cursor).await?;
+                    Ok((page.resources, page.next_cursor))
+                })
+            })
+            .await?;
+        Ok(ListResourcesResult {
+            next_cursor,
+            resources,
+        })
+    }
+
+    async fn list_resource_templates(
+        &self,
+        context: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> Result<ListResourceTemplatesResult, Error> {
+        let cursor = request.and_then(|r| r.cursor);
+        let (resource_templates, next_cursor) = self
+            .paginate(cursor, |mount, cursor| {
+                let context = context.clone();
+                Box::pin(async move {
+                    let page = mount
+                        .handler
+                        .list_resource_templates(context, cursor)
+                        .await?;
+                    Ok((page.resource_templates, page.next_cursor))
+                })
+            })
+            .await?;
+        Ok(ListResourceTemplatesResult {
+            next_cursor,
+            resource_templates,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        context: Context,
+        request: ReadResourceRequestParam,
+    ) -> Result<ReadResourceResult, Error> {
+        self.first(|mount| {
+            mount
+                .handler
+                .read_resource(context.clone(), request.clone())
+        })
+        .await
+    }
+
+    async fn list_prompts(
+        &self,
+        context: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> Result<ListPromptsResult, Error> {
+        let cursor = request.and_then(|r| r.cursor);
+        let (prompts, next_cursor) = self
+            .paginate(cursor, |mount, cursor| {
+                let context = context.clone();
+                Box::pin(async move {
+                    let page = mount.handler.list_prompts(context, cursor).await?;
+                    let prompts = page
+                        .prompts
+                        .into_iter()
+                        .map(|prompt| Prompt {
+                            name: mount.qualify(&prompt.name),
+                            ..prompt
+                        })
+                        .collect();
+                    Ok((prompts, page.next_cursor))
+                })
+            })
+            .await?;
+        Ok(ListPromptsResult {
+            next_cursor,
+            prompts,
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        context: Context,
+        request: GetPromptRequestParam,
+    ) -> Result<GetPromptResult, Error> {
+        match self.route(&request.name) {
+            Some((mount, name)) => {
+                let request = GetPromptRequestParam {
+                    name: name.to_string(),
+                    arguments: request.arguments,
+                };
+                mount.handler.get_prompt(context, request).await
+            }
+            None => Err(Error::invalid_params(
+                format!("not found prompt {}", request.name),
+                None,
+            )),
+        }
+    }
+
+    async fn complete(
+        &self,
+        context: Context,
+        request: CompleteRequestParam,
+    ) -> Result<CompleteResult, Error> {
+        match &request.r#ref {
+            Reference::Prompt(prompt) => match self.route(&prompt.name) {
+                Some((mount, name)) => {
+                    let request = CompleteRequestParam {
+                        r#ref: Reference::Prompt(PromptReference {
+                            name: name.to_string(),
+                            title: None,
+                        }),
+                        argument: request.argument,
+                        context: request.context,
+                    };
+                    mount.handler.complete(context, request).await
+                }
+                None => match Handler::completions(self) {
+                    true => Err(Error::invalid_params(
+                        format!("not found prompt {}", prompt.name),
+                        None,
+                    )),
+                    false => Err(Error::method_not_found::<CompleteRequestMethod>()),
+                },
+            },
+            Reference::Resource(_) => {
+                self.first(|mount| mount.handler.complete(context.clone(), request.clone()))
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Not `super::*`: `DynHandler` would make the `Handler` method calls ambiguous.
+    use super::Compose;
+    use crate::handler::Service;
+    use crate::{model::*, Context, Error, Handler};
+    use futures::executor::block_on;
+
+    /// Lists `count` tools named `tool{i}`, `page` at a time, and echoes calls.
+    struct Pack {
+        count: usize,
+        page: usize,
+        instructions: Option<&'static str>,
+    }
+
+    impl Handler for Pack {
+        fn get_info(&self, _: Context) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                instructions: self.instructions.map(str::to_string),
+                ..Default::default()
+            }
+        }
+
+        async fn list_tools(
+            &self,
+            _: Context,
+            request: Option<PaginatedRequestParam>,
+        ) -> Result<ListToolsResult, Error> {
+            let start: usize = request
+                .and_then(|r| r.cursor)
+                .map_or(0, |c| c.parse().unwrap());
+            let end = (start + self.page).min(self.count);
+            Ok(ListToolsResult {
+                next_cursor: (end < self.count).then(|| end.to_string()),
+                tools: (start..end)
+                    .map(|i| Tool::new(format!("tool{i}"), "A tool", JsonObject::new()))
+                    .collect(),
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _: Context,
+            request: CallToolRequestParam,
+        ) -> Result<CallToolResult, Error> {
+            Ok(CallToolResult::success(
+                Content::text(request.name).into_contents(),
+            ))
+        }
+    }
+
+    struct Prompts;
+
+    impl Handler for Prompts {
+        fn get_info(&self, _: Context) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_prompts().build(),
+                instructions: Some("Ask for help".to_string()),
+                ..Default::default()
+            }
+        }
+
+        async fn list_prompts(
+            &self,
+            _: Context,
+            _: Option<PaginatedRequestParam>,
+        ) -> Result<ListPromptsResult, Error> {
+            Ok(ListPromptsResult {
+                next_cursor: None,
+                prompts: vec![Prompt::new("help", None::<String>, None)],
+            })
+        }
+
+        async fn get_prompt(
+            &self,
+            _: Context,
+            request: GetPromptRequestParam,
+        ) -> Result<GetPromptResult, Error> {
+            Ok(GetPromptResult {
+                description: Some(request.name),
+                messages: vec![],
+            })
+        }
+    }
+
+    /// Serves `db://{key}` URIs whose key starts with `prefix`, echoing the matched template.
+    struct Table {
+        template: &'static str,
+        prefix: &'static str,
+    }
+
+    impl Handler for Table {
+        fn get_info(&self, _: Context) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            }
+        }
+
+        async fn list_resource_templates(
+            &self,
+            _: Context,
+            _: Option<PaginatedRequestParam>,
+        ) -> Result<ListResourceTemplatesResult, Error> {
+            Ok(ListResourceTemplatesResult {
+                next_cursor: None,
+                resource_templates: vec![RawResourceTemplate {
+                    uri_template: self.template.to_string(),
+                    name: self.prefix.to_string(),
+                    title: None,
+                    description: None,
+                    mime_type: None,
+                }
+                .no_annotation()],
+            })
+        }
+
+        async fn read_resource(
+            &self,
+            context: Context,
+            request: ReadResourceRequestParam,
+        ) -> Result<ReadResourceResult, Error> {
+            let key = context.uri_params.iter().next().map(|(_, value)| value);
+            if !key.is_some_and(|key| key.starts_with(self.prefix)) {
+                return Err(Error::resource_not_found(request.uri, None));
+            }
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::text(
+                    context.resource_template.unwrap_or_default(),
+                    request.uri,
+                )],
+            })
+        }
+    }
+
+    fn compose() -> Compose {
+        Compose::new()
+            .mount(
+                "ledger",
+                Pack {
+                    count: 3,
+                    page: 2,
+                    instructions: Some("Move tokens"),
+                },
+            )
+            .mount("help", Prompts)
+            .mount(
+                "ledger_v2",
+                Pack {
+                    count: 1,
+                    page: 10,
+                    instructions: None,
+                },
+            )
+    }
+
+    fn call(compose: &Compose, name: &str) -> Result<CallToolResult, Error> {
+        block_on(compose.call_tool(
+            Context::default(),
+            CallToolRequestParam {
+                name: name.to_string().into(),
+                arguments: None,
+            },
+        ))
+    }
+
+    #[test]
+    fn test_info() {
+        let info = compose().get_info(Context::default());
+        assert_eq!(
+            info.capabilities,
+            ServerCapabilities::builder()
+                .enable_prompts()
+                .enable_tools()
+                .build()
+        );
+        assert_eq!(
+            info.instructions.as_deref(),
+            Some("ledger: Move tokens\n\nhelp: Ask for help")
+        );
+    }
+
+    #[test]
+    fn test_tools() {
+        let compose = compose();
+        let mut names = Vec::new();
+        let mut cursors = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = block_on(
+                compose.list_tools(Context::default(), Some(PaginatedRequestParam { cursor })),
+            )
+            .unwrap();
+            names.extend(page.tools.into_iter().map(|t| t.name.to_string()));
+            match page.next_cursor {
+                Some(next) => {
+                    cursors.push(next.clone());
+                    cursor = Some(next);
+                }
+                None => break,
+            }
+        }
+        assert_eq!(
+            names,
+            vec![
+                "ledger_tool0",
+                "ledger_tool1",
+                "ledger_tool2",
+                "ledger_v2_tool0"
+            ]
+        );
+        assert_eq!(cursors, vec!["0:2", "1:"]);
+
+        assert_eq!(
+            block_on(compose.list_tools(
+                Context::default(),
+                Some(PaginatedRequestParam {
+                    cursor: Some("9:".to_string())
+                })
+            )),
+            Err(Error::invalid_params("invalid cursor", None))
+        );
+
+        assert_eq!(
+            call(&compose, "ledger_tool1"),
+            Ok(CallToolResult::success(
+                Content::text("tool1").into_contents()
+            ))
+        );
+        assert_eq!(
+            call(&compose, "ledger_v2_tool0"),
+            Ok(CallToolResult::success(
+                Content::text("tool0").into_contents()
+            ))
+        );
+        assert_eq!(
+            call(&compose, "admin_tool0"),
+            Err(Error::invalid_params("not found tool admin_tool0", None))
+        );
+        assert_eq!(
+            call(&compose, "help_help"),
+            Err(Error::method_not_found::<CallToolRequestMethod>())
+        );
+    }
+
+    #[test]
+    fn test_prompts() {
+        let compose = compose();
+        let page = block_on(compose.list_prompts(Context::default(), None)).unwrap();
+        assert_eq!(
+            page.prompts,
+            vec![Prompt::new("help_help", None::<String>, None)]
+        );
+        assert_eq!(page.next_cursor, Some("2:".to_string()));
+        assert_eq!(
+            block_on(compose.list_prompts(
+                Context::default(),
+                Some(PaginatedRequestParam {
+                    cursor: page.next_cursor
+                })
+            )),
+            Ok(ListPromptsResult::default())
+        );
+
+        assert_eq!(
+            block_on(compose.get_prompt(
+                Context::default(),
+                GetPromptRequestParam {
+                    name: "help_help".to_string(),
+                    arguments: None,
+                }
+            ))
+            .map(|r| r.description),
+            Ok(Some("help".to_string()))
+        );
+
+        let unknown = CompleteRequestParam {
+            r#ref: Reference::Prompt(PromptReference {
+                name: String::new(),
+                title: None,
+            }),
+            argument: ArgumentInfo {
+                name: String::new(),
+                value: String::new(),
+            },
+            context: None,
+        };
+        assert_eq!(
+            block_on(compose.complete(Context::default(), unknown)),
+            Err(Error::method_not_found::<CompleteRequestMethod>())
+        );
+        assert_eq!(
+            block_on(compose.read_resource(
+                Context::default(),
+                ReadResourceRequestParam {
+                    uri: "file:///x".to_string()
+                }
+            )),
+            Err(Error::method_not_found::<ReadResourceRequestMethod>())
+        );
+    }
+
+    #[test]
+    fn test_read_resource() {
+        let compose = Compose::new()
+            .mount(
+                "users",
+                Table {
+                    template: "db://{user}",
+                    prefix: "u",
+                },
+            )
+            .mount(
+                "orders",
+                Table {
+                    template: "db://{order}",
+                    prefix: "o",
+                },
+            );
+        let read = |uri: &str| {
+            let context = block_on(compose.match_template(Context::default(), uri));
+            block_on(compose.read_resource(
+                context,
+                ReadResourceRequestParam {
+                    uri: uri.to_string(),
+                },
+            ))
+        };
+        assert_eq!(
+            read("db://o1").map(|r| r.contents),
+            Ok(vec![ResourceContents::text("db://{order}", "db://o1")])
+        );
+        assert_eq!(
+            read("db://u1").map(|r| r.contents),
+            Ok(vec![ResourceContents::text("db://{user}", "db://u1")])
+        );
+    }
+
+    #[test]
+    fn test_certification() {
+        use crate::{init_certification, Server, ToolRegistry};
+        use ic_http_certification::{HttpRequest, Method};
+
+        let registry = ToolRegistry::new();
+        let compose = Compose::new().mount("reg", registry.clone());
+        init_certification(&compose);
+
+        let list = HttpRequest::builder()
+            .with_method(Method::POST)
+            .with_url("/mcp")
+            .with_body(br#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#.to_vec())
+            .build();
+        let query = || block_on(compose.handle_query(&list, |_| true));
+        assert_eq!(query().upgrade(), None);
+
+        registry.register("ping", "Reply pong", JsonObject::new(), |_, _| async {
+            Ok(CallToolResult::success(vec![]))
+        });
+        assert_eq!(query().upgrade(), Some(true));
+        block_on(compose.handle(&list, |_| true));
+        let res = query();
+        assert_eq!(res.upgrade(), None);
+        assert!(String::from_utf8(res.body().to_vec())
+            .unwrap()
+            .contains(r#""name":"reg_ping""#));
+    }
+}
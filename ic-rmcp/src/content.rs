@@ -0,0 +1,230 @@
+//! Helpers for building tool result content.
+//!
+//! Every helper checks the size of the item, serialized as JSON, against [`MAX_RESPONSE_BYTES`]
+//! and returns an `internal_error` if it could not fit in a response; use [`check_size`] on the
+//! assembled result when combining several items. Both leave room for what the response adds
+//! around the result, and the server still answers with an error when the serialized response
+//! turns out too large.
+//!
+//! ```ignore
+//! let chart: Vec<u8> = render_chart(&balances);
+//! Ok(CallToolResult::success(vec![
+//!     pretty_json_content(&balances)?,
+//!     image_content(&chart, "image/png")?,
+//!     resource_link(&RawResource::new("ledger://history", "Transaction history").no_annotation())?,
+//! ]))
+//! ```
+//!
+//! `resource_link` items were introduced in protocol `2025-06-18`; clients that negotiated an
+//! earlier version get a text item naming the resource instead.
+use crate::{
+    model::{
+        Annotated, CallToolResult, Content, RawAudioContent, RawContent, Resource, ResourceContents,
+    },
+    Error,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Serialize;
+use serde_json::{to_string, to_string_pretty, to_vec};
+
+/// Largest HTTP response body the IC delivers to clients: 2 MiB.
+pub const MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Room left around a tool result: the JSON-RPC envelope, `text/event-stream` framing, log and
+/// progress notifications streamed before the result, and response headers such as the
+/// certificate of query responses.
+const RESERVED_BYTES: usize = 64 * 1024;
+
+/// A text item holding `value` as compact JSON.
+pub fn json_content<T: Serialize>(value: &T) -> Result<Content, Error> {
+    let json = to_string(value).map_err(|err| Error::internal_error(err.to_string(), None))?;
+    checked(Content::text(json))
+}
+
+/// A text item holding `value` as indented JSON, easier for models and people to read.
+pub fn pretty_json_content<T: Serialize>(value: &T) -> Result<Content, Error> {
+    let json =
+        to_string_pretty(value).map_err(|err| Error::internal_error(err.to_string(), None))?;
+    checked(Content::text(json))
+}
+
+/// An image item with the base64 encoding of `data`, such as a PNG chart rendered in the canister.
+pub fn image_content(data: &[u8], mime_type: &str) -> Result<Content, Error> {
+    checked(Content::image(STANDARD.encode(data), mime_type))
+}
+
+/// An audio item with the base64 encoding of `data`.
+pub fn audio_content(data: &[u8], mime_type: &str) -> Result<Content, Error> {
+    checked(Annotated::new(
+        RawContent::Audio(RawAudioContent {
+            data: STANDARD.encode(data),
+            mime_type: mime_type.to_string(),
+        }),
+        None,
+    ))
+}
+
+/// An embedded text resource.
+pub fn embedded_text(
+    uri: &str,
+    mime_type: &str,
+    text: impl Into<String>,
+) -> Result<Content, Error> {
+    checked(Content::resource(ResourceContents::TextResourceContents {
+        uri: uri.to_string(),
+        mime_type: Some(mime_type.to_string()),
+        text: text.into(),
+        meta: None,
+    }))
+}
+
+/// An embedded binary resource, base64-encoded.
+pub fn embedded_blob(uri: &str, mime_type: &str, data: &[u8]) -> Result<Content, Error> {
+    checked(Content::resource(ResourceContents::BlobResourceContents {
+        uri: uri.to_string(),
+        mime_type: Some(mime_type.to_string()),
+        blob: STANDARD.encode(data),
+        meta: None,
+    }))
+}
+
+/// A link to a resource the server can read, typically one returned by `resources/list`.
+pub fn resource_link(resource: &Resource) -> Result<Content, Error> {
+    checked(Annotated::new(
+        RawContent::ResourceLink(resource.raw.clone()),
+        resource.annotations.clone(),
+    ))
+}
+
+/// Check that `result` fits in a response.
+///
+/// The result is measured as serialized JSON, so escaped characters are counted as sent, and a
+/// [`structured_result`](crate::structured_result) counts both its `structuredContent` and its
+/// text fallback.
+pub fn check_size(result: &CallToolResult) -> Result<(), Error> {
+    size(result).map(|_| ())
+}
+
+fn checked(content: Content) -> Result<Content, Error> {
+    size(&content)?;
+    Ok(content)
+}
+
+fn size<T: Serialize>(value: &T) -> Result<usize, Error> {
+    let size = to_vec(value)
+        .map_err(|err| Error::internal_error(err.to_string(), None))?
+        .len();
+    match size + RESERVED_BYTES > MAX_RESPONSE_BYTES {
+        true => Err(Error::internal_error(
+            format!(
+                "tool result of {size} bytes exceeds the {MAX_RESPONSE_BYTES} bytes response limit"
+            ),
+            None,
+        )),
+        false => Ok(size),
+    }
+}
+
+/// Replace the `resource_link` items of `result` by text items naming the resource, for clients
+/// older than `2025-06-18`.
+pub(crate) fn strip_resource_links(result: &mut CallToolResult) {
+    for item in result.content.iter_mut() {
+        if let RawContent::ResourceLink(link) = &item.raw {
+            *item = Content::text(format!("{}: {}", link.name, link.uri));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AnnotateAble, RawResource};
+    use serde_json::json;
+
+    #[test]
+    fn test_content() {
+        assert_eq!(
+            json_content(&json!({"a": [1, 2]})),
+            Ok(Content::text(r#"{"a":[1,2]}"#))
+        );
+        assert_eq!(
+            pretty_json_content(&json!({"a": 1})),
+            Ok(Content::text("{\n  \"a\": 1\n}"))
+        );
+        assert_eq!(
+            image_content(b"png", "image/png"),
+            Ok(Content::image("cG5n", "image/png"))
+        );
+        assert_eq!(
+            serde_json::to_value(audio_content(b"wav", "audio/wav").unwrap()).unwrap(),
+            json!({"type": "audio", "data": "d2F2", "mimeType": "audio/wav"})
+        );
+
+        assert_eq!(
+            serde_json::to_value(embedded_blob("ledger://logo", "image/png", b"png").unwrap())
+                .unwrap(),
+            json!({"type": "resource", "resource": {"uri": "ledger://logo", "mimeType": "image/png", "blob": "cG5n"}})
+        );
+
+        let large = vec![0u8; MAX_RESPONSE_BYTES];
+        assert!(image_content(&large, "image/png").is_err());
+        assert!(embedded_text(
+            "ledger://dump",
+            "text/plain",
+            "x".repeat(MAX_RESPONSE_BYTES)
+        )
+        .is_err());
+
+        let half = embedded_text(
+            "ledger://dump",
+            "text/plain",
+            "x".repeat(MAX_RESPONSE_BYTES / 2),
+        )
+        .unwrap();
+        assert_eq!(
+            check_size(&CallToolResult::success(vec![half.clone()])),
+            Ok(())
+        );
+        assert!(check_size(&CallToolResult::success(vec![half.clone(), half])).is_err());
+
+        // Escaped characters and the text fallback of structured results count as sent.
+        assert!(embedded_text(
+            "ledger://dump",
+            "text/plain",
+            "\"".repeat(MAX_RESPONSE_BYTES / 2)
+        )
+        .is_err());
+        let dump =
+            crate::structured_result(&json!({"dump": "x".repeat(MAX_RESPONSE_BYTES * 3 / 5)}))
+                .unwrap();
+        assert!(check_size(&dump).is_err());
+    }
+
+    #[test]
+    fn test_resource_link() {
+        let mut resource = RawResource::new("ledger://history", "History");
+        resource.mime_type = Some("application/json".to_string());
+        let mut result = CallToolResult::success(vec![
+            Content::text("See the history"),
+            resource_link(&resource.no_annotation()).unwrap(),
+        ]);
+        assert_eq!(
+            serde_json::to_value(&result.content[1]).unwrap(),
+            json!({
+                "type": "resource_link",
+                "uri": "ledger://history",
+                "name": "History",
+                "mimeType": "application/json"
+            })
+        );
+
+        strip_resource_links(&mut result);
+        assert_eq!(
+            result.content,
+            vec![
+                Content::text("See the history"),
+                Content::text("History: ledger://history")
+            ]
+        );
+    }
+}
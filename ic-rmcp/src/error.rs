@@ -0,0 +1,125 @@
+//! Tool errors, split the way the MCP specification expects.
+//!
+//! - Failures while running a tool (a rejected inter-canister call, a failed HTTPS outcall,
+//!   insufficient balance) are tool results with `isError` set, so the model can read them and
+//!   recover.
+//! - Requests that cannot be served (unknown tool, malformed arguments) are JSON-RPC errors.
+//!
+//! Tools written against [`ToolError`] can use `?` on any error type: [`Error`]s such as those
+//! from [`parse_arguments`](crate::parse_arguments) stay JSON-RPC errors, everything else
+//! becomes an error result.
+//!
+//! ```ignore
+//! #[tool]
+//! async fn get_weather(&self, args: WeatherRequest) -> Result<CallToolResult, ToolError> {
+//!     let weather = fetch_weather(args.latitude, args.longitude).await.map_err(ToolError::new)?;
+//!     Ok(CallToolResult::success(Content::text(weather).into_contents()))
+//! }
+//! ```
+use crate::model::{CallToolResult, Content};
+use crate::Error;
+use std::any::Any;
+use std::fmt;
+
+/// An error returned by a tool.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolError {
+    /// The tool ran and failed; reported as a result with `isError` set.
+    Failed(String),
+    /// The request is invalid; reported as a JSON-RPC error.
+    Protocol(Error),
+}
+
+impl ToolError {
+    /// A tool failure with the given message.
+    pub fn new(message: impl fmt::Display) -> Self {
+        ToolError::Failed(message.to_string())
+    }
+
+    /// Convert into what `Handler::call_tool` returns.
+    pub fn into_result(self) -> Result<CallToolResult, Error> {
+        match self {
+            ToolError::Failed(message) => Ok(CallToolResult::error(vec![Content::text(message)])),
+            ToolError::Protocol(error) => Err(error),
+        }
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolError::Failed(message) => f.write_str(message),
+            ToolError::Protocol(error) => error.fmt(f),
+        }
+    }
+}
+
+// `ToolError` deliberately does not implement `std::error::Error`, which keeps this conversion
+// coherent. `Error` is recognized at runtime so that protocol errors are not turned into results.
+impl<E: std::error::Error + 'static> From<E> for ToolError {
+    fn from(error: E) -> Self {
+        match (&error as &dyn Any).downcast_ref::<Error>() {
+            Some(error) => ToolError::Protocol(error.clone()),
+            None => ToolError::new(error),
+        }
+    }
+}
+
+/// Conversion of a tool's outcome into what `Handler::call_tool` returns.
+///
+/// Implemented for `Result<CallToolResult, E>` with any error convertible into [`ToolError`],
+/// including [`Error`] itself. `#[tool]` methods may return any of these.
+pub trait IntoToolResult {
+    /// Keep successes and protocol errors, turn tool failures into error results.
+    fn into_tool_result(self) -> Result<CallToolResult, Error>;
+}
+
+impl<E: Into<ToolError>> IntoToolResult for Result<CallToolResult, E> {
+    fn into_tool_result(self) -> Result<CallToolResult, Error> {
+        self.or_else(|error| error.into().into_result())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_not_found;
+
+    fn divide(a: &str, b: &str) -> Result<CallToolResult, ToolError> {
+        let a: i64 = a.parse()?;
+        let b: i64 = b.parse()?;
+        if b == 0 {
+            return Err(ToolError::new("division by zero"));
+        }
+        Ok(CallToolResult::success(vec![Content::text(
+            (a / b).to_string(),
+        )]))
+    }
+
+    #[test]
+    fn test_into_tool_result() {
+        assert_eq!(
+            divide("6", "3").into_tool_result(),
+            Ok(CallToolResult::success(vec![Content::text("2")]))
+        );
+        assert_eq!(
+            divide("6", "0").into_tool_result(),
+            Ok(CallToolResult::error(vec![Content::text(
+                "division by zero"
+            )]))
+        );
+        assert_eq!(
+            divide("six", "3").into_tool_result(),
+            Ok(CallToolResult::error(vec![Content::text(
+                "invalid digit found in string"
+            )]))
+        );
+
+        assert_eq!(
+            ToolError::from(tool_not_found("divide")),
+            ToolError::Protocol(tool_not_found("divide"))
+        );
+        let unknown: Result<CallToolResult, Error> = Err(tool_not_found("divide"));
+        assert_eq!(unknown.into_tool_result(), Err(tool_not_found("divide")));
+    }
+}
@@ -1,6 +1,16 @@
-use crate::server::Server;
-use crate::state::fetch_jwks;
-use crate::{model::*, Error};
+use crate::certification::{certification_enabled, certified, certify};
+use crate::content::{strip_resource_links, MAX_RESPONSE_BYTES};
+use crate::logging::Notifications;
+use crate::resource::{match_resource_template, UriParams};
+use crate::server::{Server, ServerConfig};
+use crate::session::{
+    end_session, is_initialized, mark_initialized, resume_session, sessions_enabled, set_log_level,
+    start_session,
+};
+use crate::state::{cached_jwks, fetch_jwks};
+use crate::structured::{strip_output_schemas, strip_structured_content};
+use crate::validate::check_arguments;
+use crate::{model::*, Error, ToolExt};
 use ic_cdk::eprintln;
 use ic_http_certification::{HeaderField, HttpRequest, HttpResponse, StatusCode};
 use serde::Serialize;
@@ -16,12 +26,39 @@ use oauth::{validate_token, OAuthConfig};
 ///
 /// When OAuth is enabled via [`Server::handle_with_oauth`](crate::Server::handle_with_oauth),
 /// [`Context::subject`] is populated with the `sub` claim from the validated access token.
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+///
+/// For `resources/read` requests whose URI matches one of the templates returned by
+/// [`Handler::list_resource_templates`], [`Context::resource_template`] and
+/// [`Context::uri_params`] describe the match.
+///
+/// When sessions are enabled via [`init_sessions`](crate::init_sessions),
+/// [`Context::session_id`] and [`Context::client_info`] identify the calling client.
+///
+/// Use [`Context::log`] to send log messages to the client and [`Context::report_progress`] to
+/// report progress on requests carrying a `_meta.progressToken`.
+// Not `Eq`: the negotiated client `Implementation` only implements `PartialEq`.
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Context {
     pub subject: Option<String>,
+    /// URI template that matched the requested resource URI, if any.
+    pub resource_template: Option<String>,
+    /// Variables extracted from the requested resource URI by [`Context::resource_template`].
+    pub uri_params: UriParams,
+    /// Progress token sent by the client in the request's `_meta`, if any.
+    pub progress_token: Option<ProgressToken>,
+    /// Id of the client's session, if sessions are enabled.
+    pub session_id: Option<String>,
+    /// Client implementation reported on `initialize`, if sessions are enabled.
+    pub client_info: Option<Implementation>,
+    /// Protocol version negotiated for the client's session, if sessions are enabled.
+    pub(crate) protocol_version: Option<ProtocolVersion>,
+    /// Level the client set for its session with `logging/setLevel`, if any.
+    pub(crate) log_level: Option<LoggingLevel>,
+    pub(crate) notifications: Notifications,
 }
 
 type RxJsonRpcMessage = JsonRpcMessage<ClientRequest, ClientResult, ClientNotification>;
+type TxJsonRpcMessage = JsonRpcMessage<Request, ServerResult, Notification>;
 
 impl<S: Service> Server for S {
     async fn handle(
@@ -29,17 +66,57 @@ impl<S: Service> Server for S {
         req: &HttpRequest<'_>,
         auth: impl Fn(&[HeaderField]) -> bool,
     ) -> HttpResponse<'_> {
-        match auth(req.headers()) {
-            true => self.raw_handle(None, req).await,
-            false => HttpResponse::builder()
-                .with_status_code(StatusCode::from_u16(401).unwrap())
-                .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
-                .with_body(br#"Unauthorized"#)
-                .build(),
+        let res = match self.early_response(req) {
+            Some(res) => res,
+            None if auth(req.headers()) => self.raw_handle(None, req).await,
+            None => unauthorized(),
+        };
+        let res = self.with_cors(req, res);
+        if self.certifiable(req) {
+            certify(req, &res);
         }
+        res
+    }
+
+    async fn handle_query(
+        &self,
+        req: &HttpRequest<'_>,
+        auth: impl Fn(&[HeaderField]) -> bool,
+    ) -> HttpResponse<'_> {
+        let res = match self.early_response(req) {
+            Some(res) => res,
+            None if auth(req.headers()) => self.serve(None, req, true).await,
+            None => unauthorized(),
+        };
+        certified_query(req, self.with_cors(req, res))
     }
 
     async fn handle_with_oauth(&self, req: &HttpRequest<'_>, cfg: OAuthConfig) -> HttpResponse<'_> {
+        let certifiable = self.certifiable(req) || metadata_request(req, &cfg);
+        let res = self.with_cors(req, self.oauth_handle(req, cfg, false).await);
+        if certifiable {
+            certify(req, &res);
+        }
+        res
+    }
+
+    async fn handle_query_with_oauth(
+        &self,
+        req: &HttpRequest<'_>,
+        cfg: OAuthConfig,
+    ) -> HttpResponse<'_> {
+        let res = self.oauth_handle(req, cfg, true).await;
+        certified_query(req, self.with_cors(req, res))
+    }
+}
+
+pub(crate) trait Service: Handler {
+    async fn oauth_handle(
+        &self,
+        req: &HttpRequest<'_>,
+        cfg: OAuthConfig,
+        query: bool,
+    ) -> HttpResponse<'_> {
         let metadata_path = match Url::parse(&cfg.metadata_url) {
             Ok(url) => url.path().to_string(),
             Err(err) => {
@@ -52,7 +129,15 @@ impl<S: Service> Server for S {
             }
         };
 
-        if req.method() == "GET" && req.url() == metadata_path {
+        if let Some(res) = self
+            .server_config()
+            .origins
+            .and_then(|origins| origins.check(req))
+        {
+            return res;
+        }
+
+        if req.method() == "GET" && req.get_path().is_ok_and(|path| path == metadata_path) {
             #[derive(Serialize)]
             struct Metadata<'a> {
                 resource: &'a str,
@@ -78,6 +163,10 @@ impl<S: Service> Server for S {
             });
         }
 
+        if let Some(res) = self.early_response(req) {
+            return res;
+        }
+
         let token = match req
             .headers()
             .iter()
@@ -100,7 +189,13 @@ impl<S: Service> Server for S {
             }
         };
 
-        let jwk_set = match fetch_jwks(&cfg.issuer_configs.jwks_url).await {
+        // Keys are fetched with an HTTPS outcall, which queries cannot make.
+        let jwk_set = match (query, cached_jwks()) {
+            (_, Some(set)) => Ok(set),
+            (true, None) => return upgrade(),
+            (false, None) => fetch_jwks(&cfg.issuer_configs.jwks_url).await,
+        };
+        let jwk_set = match jwk_set {
             Ok(set) => set,
             Err(err) => {
                 eprintln!("fetch jwk set: {}", err);
@@ -113,7 +208,37 @@ impl<S: Service> Server for S {
         };
 
         match validate_token(token, &cfg.issuer_configs, jwk_set) {
-            Ok(claims) => self.raw_handle(Some(claims.sub), req).await,
+            Ok(claims) => {
+                if let Some(scope) = self.destructive_scope() {
+                    if !claims.has_scope(scope) {
+                        let context = Context {
+                            subject: Some(claims.sub.clone()),
+                            ..Default::default()
+                        };
+                        for name in called_tools(req.body()) {
+                            // Fail closed: only tools known to be non-destructive pass.
+                            let tool = self.find_tool(context.clone(), &name).await;
+                            if !tool.is_some_and(|tool| !tool.is_destructive()) {
+                                return HttpResponse::builder()
+                                    .with_status_code(StatusCode::from_u16(403).unwrap())
+                                    .with_headers(vec![
+                                        ("Content-Type".to_string(), "text/plain".to_string()),
+                                        (
+                                            "WWW-Authenticate".to_string(),
+                                            format!(
+                                                "Bearer error=\"insufficient_scope\", scope=\"{}\", resource_metadata=\"{}\"",
+                                                scope, cfg.metadata_url
+                                            ),
+                                        ),
+                                    ])
+                                    .with_body(br#"Insufficient scope"#)
+                                    .build();
+                            }
+                        }
+                    }
+                }
+                self.serve(Some(claims.sub), req, query).await
+            }
             Err(_err) => HttpResponse::builder()
                 .with_status_code(StatusCode::from_u16(401).unwrap())
                 .with_headers(vec![
@@ -127,18 +252,87 @@ impl<S: Service> Server for S {
                 .build(),
         }
     }
-}
 
-trait Service: Handler {
+    /// The fallthrough response to a request outside the server's paths, if one is configured.
+    fn fall_through(&self, req: &HttpRequest<'_>) -> Option<HttpResponse<'static>> {
+        let config = self.server_config();
+        match config.matches(req) {
+            true => None,
+            false => config.fallthrough.map(|fallthrough| fallthrough(req)),
+        }
+    }
+
+    /// The response to a request answered before authorization: fallthrough routes, requests
+    /// from disallowed origins and CORS preflights.
+    fn early_response(&self, req: &HttpRequest<'_>) -> Option<HttpResponse<'static>> {
+        self.fall_through(req)
+            .or_else(|| self.server_config().origins?.check(req))
+            .or_else(|| self.preflight(req))
+    }
+
+    /// The response to a CORS preflight, if a [`CorsPolicy`](crate::CorsPolicy) is configured.
+    fn preflight(&self, req: &HttpRequest<'_>) -> Option<HttpResponse<'static>> {
+        match (req.method() == "OPTIONS", self.server_config().cors) {
+            (true, Some(cors)) => Some(cors.preflight(req)),
+            _ => None,
+        }
+    }
+
+    /// Add the CORS headers of the configured [`CorsPolicy`](crate::CorsPolicy), if any, to `res`,
+    /// unless the [`OriginPolicy`](crate::OriginPolicy) rejects the request's origin.
+    fn with_cors<'a>(&self, req: &HttpRequest<'_>, res: HttpResponse<'a>) -> HttpResponse<'a> {
+        let config = self.server_config();
+        match config.cors {
+            Some(cors)
+                if config
+                    .origins
+                    .is_none_or(|origins| origins.check(req).is_none()) =>
+            {
+                cors.decorate(req, res)
+            }
+            _ => res,
+        }
+    }
+
+    /// Serve the request, or with `query`, ask for an upgrade unless it is safe to serve in a
+    /// query call.
+    async fn serve(
+        &self,
+        subject: Option<String>,
+        req: &HttpRequest<'_>,
+        query: bool,
+    ) -> HttpResponse<'_> {
+        // Gateways reject query responses without a certificate.
+        if query && !(certification_enabled() && self.certifiable(req)) {
+            return upgrade();
+        }
+        self.raw_handle(subject, req).await
+    }
     async fn raw_handle(&self, subject: Option<String>, req: &HttpRequest<'_>) -> HttpResponse<'_> {
-        if req.method() != "POST" || !req.url().ends_with("/mcp") {
+        let config = self.server_config();
+        if req.method() == "DELETE" && config.matches(req) && sessions_enabled() {
+            return end_session(req);
+        }
+
+        if req.method() != "POST" || !config.matches(req) {
             return HttpResponse::builder()
                 .with_status_code(StatusCode::from_u16(404).unwrap())
                 .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
-                .with_body(b"Not Found or Method Not Allowed. Use POST to /mcp")
+                .with_body(
+                    format!(
+                        "Not Found or Method Not Allowed. Use POST to {}",
+                        config.paths.join(" or ")
+                    )
+                    .into_bytes(),
+                )
                 .build();
         }
 
+        let context = Context {
+            subject,
+            ..Default::default()
+        };
+
         let version = match req
           .headers()
           .iter()
@@ -154,25 +348,41 @@ trait Service: Handler {
               },
               None => None,
           };
-
         match from_slice::<Value>(req.body()){
-            Ok(Value::Array(req)) => {
-                    if version.is_some_and(|ver| ver.partial_cmp(&protocol_version_2025_06_18()) != Some(Ordering::Less)) {
+            Ok(Value::Array(batch)) => {
+                    if version.as_ref().is_some_and(|ver| ver.partial_cmp(&protocol_version_2025_06_18()) != Some(Ordering::Less)) {
                         HttpResponse::builder()
                               .with_status_code(StatusCode::from_u16(200).unwrap())
                               .with_headers(vec![("Content-Type".to_string(), "application/json".to_string())])
                               .with_body(br#"{"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null}"#)
                               .build()
+                    } else if batch.iter().any(|message| message.get("method").and_then(Value::as_str) == Some("initialize")) {
+                        // The batch would run before the session `initialize` opens, so reject
+                        // every request in it, as `initialize` must not be batched.
+                        let errors: Vec<Value> = batch
+                            .iter()
+                            .filter_map(|message| message.get("id"))
+                            .map(|id| json!({"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid Request: initialize must not be part of a batch"},"id":id}))
+                            .collect();
+                        reply(req, vec![], errors)
                     } else {
+                        let context = match resume_session(req, context) {
+                            Ok(context) => context,
+                            Err(res) => return res,
+                        };
+                        let structured = supports_structured(&context, version.as_ref());
                         let mut results = Vec::new();
-                    for message in req {
-                        match from_value::<JsonRpcBatchRequestItem<ClientRequest, ClientNotification>>(message) {
-                            Ok(JsonRpcBatchRequestItem::Request(r)) => {
-                                results.push(to_value(self.handle_request(subject.clone(),r).await).unwrap_or(json!({"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error"}})))
+                    for message in batch {
+                        match from_value::<RxJsonRpcMessage>(message) {
+                            Ok(JsonRpcMessage::Request(r)) => {
+                                results.push(to_value(render(self.handle_request(context.clone(),r).await, structured)).unwrap_or(json!({"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error"}})))
                             }
-                            Ok(JsonRpcBatchRequestItem::Notification(n)) => {
-                                self.handle_notification(n).await
+                            Ok(JsonRpcMessage::Notification(n)) => {
+                                self.handle_notification(context.clone(), n).await
                             },
+                            Ok(_) => {
+                                results.push(json!({"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid Request"},"id":null}))
+                            }
                             Err(e) => {
                                 eprintln!("Parse JSON: {}", e);
                                 results.push(json!({"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid Request"},"id":null}))
@@ -180,14 +390,54 @@ trait Service: Handler {
                         };
                     }
 
-                    response(results)
+                    reply(req, context.notifications.take(), results)
                     }
             },
-            Ok(Value::Object(req)) => {
-                match from_value::<RxJsonRpcMessage>(Value::Object(req)) {
-                    Ok(JsonRpcMessage::Request(request)) => response(self.handle_request(subject, request).await),
+            Ok(Value::Object(body)) => {
+                match from_value::<RxJsonRpcMessage>(Value::Object(body)) {
+                    Ok(JsonRpcMessage::Request(request)) => {
+                        let initialize = match &request.request {
+                            ClientRequest::InitializeRequest(request) => Some(request.params.clone()),
+                            _ => None,
+                        };
+                        let context = match initialize {
+                            Some(_) => context,
+                            None => match resume_session(req, context) {
+                                Ok(context) => context,
+                                Err(res) => return res,
+                            },
+                        };
+
+                        let structured = supports_structured(&context, version.as_ref());
+                        let message = self.handle_request(context.clone(), request).await;
+                        let session = match (&initialize, &message) {
+                            (Some(params), JsonRpcMessage::Response(JsonRpcResponse { result: ServerResult::InitializeResult(info), .. })) => {
+                                start_session(params, info.protocol_version.clone()).await
+                            }
+                            _ => Ok(None),
+                        };
+
+                        let mut res = reply(req, context.notifications.take(), render(message, structured));
+                        match session {
+                            Ok(Some(id)) => res.add_header(("Mcp-Session-Id".to_string(), id)),
+                            Ok(None) => {}
+                            Err(err) => {
+                                eprintln!("Start session: {}", err);
+                                return HttpResponse::builder()
+                                    .with_status_code(StatusCode::from_u16(500).unwrap())
+                                    .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+                                    .with_body(br#"Internal Error"#)
+                                    .build();
+                            }
+                        }
+                        res
+                    }
                     Ok(JsonRpcMessage::Notification(notification)) => {
-                            self.handle_notification(notification).await;
+                            let context = match resume_session(req, context) {
+                                Ok(context) => context,
+                                Err(res) => return res,
+                            };
+                            self.handle_notification(context, notification).await;
                             HttpResponse::builder()
                                             .with_status_code(StatusCode::from_u16(202).unwrap())
                                             .build()
@@ -217,22 +467,54 @@ trait Service: Handler {
     }
     async fn handle_request(
         &self,
-        subject: Option<String>,
+        context: Context,
         request: JsonRpcRequest<ClientRequest>,
     ) -> JsonRpcMessage<Request, ServerResult, Notification>;
-    async fn handle_notification(&self, notification: JsonRpcNotification<ClientNotification>);
+    async fn handle_notification(
+        &self,
+        context: Context,
+        notification: JsonRpcNotification<ClientNotification>,
+    );
+    async fn match_template(&self, context: Context, uri: &str) -> Context;
+    async fn find_tool(&self, context: Context, name: &str) -> Option<Tool>;
+    fn certifiable(&self, req: &HttpRequest<'_>) -> bool;
+    async fn check_tool_arguments(
+        &self,
+        context: Context,
+        request: &CallToolRequestParam,
+    ) -> Result<(), Error>;
 }
 
 impl<H: Handler> Service for H {
     async fn handle_request(
         &self,
-        subject: Option<String>,
+        context: Context,
         request: JsonRpcRequest<ClientRequest>,
     ) -> JsonRpcMessage<Request, ServerResult, Notification> {
+        if !is_initialized(&context) && !matches!(request.request, ClientRequest::PingRequest(_)) {
+            return JsonRpcMessage::error(
+                Error::invalid_request("Server not initialized", None),
+                request.id,
+            );
+        }
+
+        let context = Context {
+            progress_token: request.request.get_meta().get_progress_token(),
+            ..context
+        };
+
         let result = match request.request {
             ClientRequest::InitializeRequest(request) => {
-                let mut info = self.get_info(Context { subject });
+                let mut info = self.get_info(context.clone());
                 info.protocol_version = protocol_version_2025_06_18();
+                info.capabilities
+                    .logging
+                    .get_or_insert_with(JsonObject::new);
+                if self.completions() {
+                    info.capabilities
+                        .completions
+                        .get_or_insert_with(JsonObject::new);
+                }
 
                 if let Some(Ordering::Equal) = request
                     .params
@@ -252,14 +534,52 @@ impl<H: Handler> Service for H {
                 }
             }
             ClientRequest::PingRequest(_) => Ok(ServerResult::empty(())),
-            ClientRequest::CallToolRequest(request) => self
-                .call_tool(Context { subject }, request.params)
-                .await
-                .map(ServerResult::CallToolResult),
+            ClientRequest::SetLevelRequest(request) => {
+                set_log_level(&context, request.params.level);
+                Ok(ServerResult::empty(()))
+            }
+            ClientRequest::CallToolRequest(request) => {
+                match self
+                    .check_tool_arguments(context.clone(), &request.params)
+                    .await
+                {
+                    Ok(()) => self
+                        .call_tool(context, request.params)
+                        .await
+                        .map(ServerResult::CallToolResult),
+                    Err(err) => Err(err),
+                }
+            }
             ClientRequest::ListToolsRequest(request) => self
-                .list_tools(Context { subject }, request.params)
+                .list_tools(context, request.params)
                 .await
                 .map(ServerResult::ListToolsResult),
+            ClientRequest::ListResourcesRequest(request) => self
+                .list_resources(context, request.params)
+                .await
+                .map(ServerResult::ListResourcesResult),
+            ClientRequest::ListResourceTemplatesRequest(request) => self
+                .list_resource_templates(context, request.params)
+                .await
+                .map(ServerResult::ListResourceTemplatesResult),
+            ClientRequest::ReadResourceRequest(request) => {
+                let context = self.match_template(context, &request.params.uri).await;
+                self.read_resource(context, request.params)
+                    .await
+                    .map(ServerResult::ReadResourceResult)
+            }
+            ClientRequest::ListPromptsRequest(request) => self
+                .list_prompts(context, request.params)
+                .await
+                .map(ServerResult::ListPromptsResult),
+            ClientRequest::GetPromptRequest(request) => self
+                .get_prompt(context, request.params)
+                .await
+                .map(ServerResult::GetPromptResult),
+            ClientRequest::CompleteRequest(request) => self
+                .complete(context, request.params)
+                .await
+                .map(ServerResult::CompleteResult),
             _ => Err(Error::new(
                 ErrorCode::METHOD_NOT_FOUND,
                 "Method not found",
@@ -272,15 +592,330 @@ impl<H: Handler> Service for H {
             Err(error) => JsonRpcMessage::error(error, request.id),
         }
     }
-    async fn handle_notification(&self, notification: JsonRpcNotification<ClientNotification>) {
-        if let ClientNotification::InitializedNotification(_) = notification.notification {}
+    async fn handle_notification(
+        &self,
+        context: Context,
+        notification: JsonRpcNotification<ClientNotification>,
+    ) {
+        if let ClientNotification::InitializedNotification(_) = notification.notification {
+            mark_initialized(&context);
+        }
+    }
+    async fn match_template(&self, mut context: Context, uri: &str) -> Context {
+        let mut cursors = Vec::new();
+        loop {
+            let page = match self
+                .list_resource_templates(
+                    context.clone(),
+                    Some(PaginatedRequestParam {
+                        cursor: cursors.last().cloned(),
+                    }),
+                )
+                .await
+            {
+                Ok(page) => page,
+                Err(err) => {
+                    eprintln!("List resource templates: {}", err);
+                    return context;
+                }
+            };
+
+            if let Some((template, params)) = match_resource_template(&page.resource_templates, uri)
+            {
+                context.resource_template = Some(template);
+                context.uri_params = params;
+                return context;
+            }
+
+            if !next_page(&mut cursors, page.next_cursor) {
+                return context;
+            }
+        }
+    }
+    async fn find_tool(&self, context: Context, name: &str) -> Option<Tool> {
+        let mut cursors = Vec::new();
+        loop {
+            let page = match self
+                .list_tools(
+                    context.clone(),
+                    Some(PaginatedRequestParam {
+                        cursor: cursors.last().cloned(),
+                    }),
+                )
+                .await
+            {
+                Ok(page) => page,
+                Err(err) => {
+                    eprintln!("List tools: {}", err);
+                    return None;
+                }
+            };
+
+            if let Some(tool) = page.tools.into_iter().find(|tool| tool.name == name) {
+                return Some(tool);
+            }
+
+            if !next_page(&mut cursors, page.next_cursor) {
+                return None;
+            }
+        }
+    }
+    /// Whether the response to `req` may be certified and served from a query: a CORS
+    /// preflight, or a single JSON-RPC request that pings, lists tools, initializes without
+    /// sessions or calls a tool opted in with [`Handler::query_tool`].
+    fn certifiable(&self, req: &HttpRequest<'_>) -> bool {
+        let config = self.server_config();
+        if !config.matches(req) {
+            return false;
+        }
+        match req.method().as_str() {
+            "OPTIONS" => return config.cors.is_some(),
+            "POST" => {}
+            _ => return false,
+        }
+        let Ok(message @ Value::Object(_)) = from_slice::<Value>(req.body()) else {
+            return false;
+        };
+        message.get("id").is_some()
+            && match message.get("method").and_then(Value::as_str) {
+                // `initialize` writes session state.
+                Some("initialize") => !sessions_enabled(),
+                Some("ping") | Some("tools/list") => true,
+                Some("tools/call") => message
+                    .pointer("/params/name")
+                    .and_then(Value::as_str)
+                    .is_some_and(|name| self.query_tool(name)),
+                _ => false,
+            }
+    }
+    async fn check_tool_arguments(
+        &self,
+        context: Context,
+        request: &CallToolRequestParam,
+    ) -> Result<(), Error> {
+        if !self.validate_arguments() {
+            return Ok(());
+        }
+
+        match self.find_tool(context, &request.name).await {
+            Some(tool) => check_arguments(&tool, request.arguments.as_ref()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Most pages [`Service`] walks through when searching a list on the server's behalf.
+const MAX_PAGES: usize = 64;
+
+/// Record `next` as the cursor of the following page, returning whether to fetch it.
+///
+/// Stops at the last page, after [`MAX_PAGES`] pages, or when a handler returns a cursor it
+/// already returned, which would otherwise loop forever.
+fn next_page(cursors: &mut Vec<String>, next: Option<String>) -> bool {
+    match next {
+        Some(next) if cursors.len() + 1 < MAX_PAGES && !cursors.contains(&next) => {
+            cursors.push(next);
+            true
+        }
+        Some(_) => {
+            eprintln!("Stopped paging after {} pages", cursors.len() + 1);
+            false
+        }
+        None => false,
     }
 }
 
+/// Prepare a `tools/list` or `tools/call` response for a client that does (or does not) support
+/// structured output and resource links; other responses are left untouched.
+fn render(mut message: TxJsonRpcMessage, structured: bool) -> TxJsonRpcMessage {
+    if let (JsonRpcMessage::Response(JsonRpcResponse { result, .. }), false) =
+        (&mut message, structured)
+    {
+        match result {
+            ServerResult::ListToolsResult(result) => strip_output_schemas(result),
+            ServerResult::CallToolResult(result) => {
+                strip_structured_content(result);
+                strip_resource_links(result);
+            }
+            _ => {}
+        }
+    }
+    message
+}
+
+/// Whether the client speaks `2025-06-18` or later, which introduced `outputSchema`,
+/// `structuredContent` and `resource_link` items.
+///
+/// The version negotiated for the client's session takes precedence over the
+/// `MCP-Protocol-Version` header, which is all there is to go by without sessions.
+fn supports_structured(context: &Context, header: Option<&ProtocolVersion>) -> bool {
+    context
+        .protocol_version
+        .as_ref()
+        .or(header)
+        .is_some_and(|ver| ver.partial_cmp(&protocol_version_2025_06_18()) != Some(Ordering::Less))
+}
+
+fn unauthorized() -> HttpResponse<'static> {
+    HttpResponse::builder()
+        .with_status_code(StatusCode::from_u16(401).unwrap())
+        .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+        .with_body(br#"Unauthorized"#)
+        .build()
+}
+
+/// Ask the HTTP gateway to repeat the request as an `http_request_update` call.
+fn upgrade() -> HttpResponse<'static> {
+    HttpResponse::builder()
+        .with_status_code(StatusCode::from_u16(200).unwrap())
+        .with_upgrade(true)
+        .build()
+}
+
+/// Replace a query response by its certified copy, or ask for an upgrade if it has none, as
+/// gateways reject uncertified query responses. Responses that carry their own certificate, such
+/// as those of a fallthrough route serving certified assets, are passed through.
+fn certified_query<'a>(req: &HttpRequest, res: HttpResponse<'a>) -> HttpResponse<'a> {
+    let own_certificate = res
+        .headers()
+        .iter()
+        .any(|(key, _)| key.eq_ignore_ascii_case("IC-Certificate"));
+    if res.upgrade() == Some(true) || own_certificate {
+        return res;
+    }
+    certified(req, &res).unwrap_or_else(upgrade)
+}
+
+/// Whether `req` fetches the OAuth protected resource metadata document of `cfg`.
+fn metadata_request(req: &HttpRequest, cfg: &OAuthConfig) -> bool {
+    req.method() == "GET"
+        && Url::parse(&cfg.metadata_url)
+            .is_ok_and(|url| req.get_path().is_ok_and(|path| path == url.path()))
+}
+
+/// Names of the tools called by a `tools/call` request or batch, ignoring malformed messages.
+fn called_tools(body: &[u8]) -> Vec<String> {
+    let messages = match from_slice::<Value>(body) {
+        Ok(Value::Array(batch)) => batch,
+        Ok(message) => vec![message],
+        Err(_) => return vec![],
+    };
+    messages
+        .iter()
+        .filter(|message| message.get("method").and_then(Value::as_str) == Some("tools/call"))
+        // A missing name matches no tool, so the scope guard denies the call.
+        .map(|message| {
+            message
+                .pointer("/params/name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect()
+}
+
 fn protocol_version_2025_06_18() -> ProtocolVersion {
     from_str::<ProtocolVersion>("\"2025-06-18\"").unwrap()
 }
 
+/// Response formats allowed by the client's `Accept` header.
+#[derive(Debug, PartialEq, Eq)]
+struct Accept {
+    json: bool,
+    event_stream: bool,
+}
+
+fn accept(req: &HttpRequest) -> Accept {
+    let mut accept = Accept {
+        json: false,
+        event_stream: false,
+    };
+    let mut present = false;
+
+    for range in req
+        .headers()
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("Accept"))
+        .flat_map(|(_, value)| value.split(','))
+    {
+        present = true;
+        let mut params = range.split(';');
+        let media = params
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if quality <= 0.0 {
+            continue;
+        }
+        match media.as_str() {
+            "application/json" | "application/*" | "*/*" => accept.json = true,
+            "text/event-stream" | "text/*" => accept.event_stream = true,
+            _ => {}
+        }
+    }
+
+    // Without an `Accept` header, or when nothing usable is listed, answer with plain JSON.
+    if !present || !accept.event_stream {
+        accept.json = true;
+    }
+    accept
+}
+
+/// Reply with a `text/event-stream` carrying `notifications` followed by `data` when the client
+/// accepts it and there is something to stream (or JSON is not acceptable), else with JSON.
+///
+/// Notifications are dropped from JSON replies; they have already been written to the canister
+/// log.
+fn reply<T: Serialize>(
+    req: &HttpRequest,
+    notifications: Vec<Value>,
+    data: T,
+) -> HttpResponse<'static> {
+    let accept = accept(req);
+    if accept.event_stream && (!notifications.is_empty() || !accept.json) {
+        sse_response(notifications, data)
+    } else {
+        response(data)
+    }
+}
+
+fn sse_response<T: Serialize>(notifications: Vec<Value>, data: T) -> HttpResponse<'static> {
+    let mut body = String::new();
+    for event in notifications
+        .iter()
+        .map(serde_json::to_string)
+        .chain([serde_json::to_string(&data)])
+    {
+        match event {
+            Ok(event) => body.push_str(&format!("event: message\ndata: {event}\n\n")),
+            Err(e) => {
+                eprintln!("Serialize response: {}", e);
+                return response(
+                    json!({"jsonrpc": "2.0", "error": {"code": -32603, "message": "Internal error"}}),
+                );
+            }
+        }
+    }
+    if body.len() > MAX_RESPONSE_BYTES {
+        return response(too_large(body.len()));
+    }
+
+    HttpResponse::builder()
+        .with_status_code(StatusCode::from_u16(200).unwrap())
+        .with_headers(vec![(
+            "Content-Type".to_string(),
+            "text/event-stream".to_string(),
+        )])
+        .with_body(body.into_bytes())
+        .build()
+}
+
 fn response<T: Serialize>(data: T) -> HttpResponse<'static> {
     let builder = HttpResponse::builder()
         .with_status_code(StatusCode::from_u16(200).unwrap())
@@ -289,6 +924,7 @@ fn response<T: Serialize>(data: T) -> HttpResponse<'static> {
             "application/json".to_string(),
         )]);
     match serde_json::to_string(&data) {
+        Ok(body) if body.len() > MAX_RESPONSE_BYTES => response(too_large(body.len())),
         Ok(body) => builder.with_body(body.into_bytes()).build(),
         Err(e) => {
             eprintln!("Serialize response: {}", e);
@@ -301,12 +937,24 @@ fn response<T: Serialize>(data: T) -> HttpResponse<'static> {
     }
 }
 
+/// The error sent instead of a response the IC would not deliver.
+fn too_large(size: usize) -> Value {
+    eprintln!("Response of {size} bytes exceeds {MAX_RESPONSE_BYTES} bytes");
+    json!({"jsonrpc": "2.0", "error": {"code": -32603, "message": format!("Internal error: response of {size} bytes exceeds the {MAX_RESPONSE_BYTES} bytes limit")}})
+}
+
 /// Define your server's MCP behavior by implementing this trait.
 ///
 /// You may override any combination of methods; defaults are provided for convenience.
 /// - [`Handler::get_info`] should describe your server and enabled capabilities
 /// - [`Handler::list_tools`] should return the tools your server exposes
 /// - [`Handler::call_tool`] should execute a requested tool and return its result
+/// - [`Handler::list_resources`] and [`Handler::read_resource`] expose readable canister data
+/// - [`Handler::list_resource_templates`] advertises parameterized resource URIs
+/// - [`Handler::list_prompts`] and [`Handler::get_prompt`] serve canned prompt templates
+/// - [`Handler::complete`] autocompletes prompt and resource template arguments
+/// - [`Handler::validate_arguments`] opts into checking tool arguments against their schema
+/// - [`Handler::server_config`] mounts the server at other paths than `/mcp`
 #[allow(unused_variables)]
 pub trait Handler {
     /// Handle a `tools/call` request.
@@ -321,6 +969,10 @@ pub trait Handler {
     }
     /// Handle a `tools/list` request.
     ///
+    /// With [`init_certification`](crate::init_certification), the result is certified and served
+    /// from queries, so call [`invalidate_certified_responses`](crate::invalidate_certified_responses)
+    /// from the update call that changes it. `ToolRegistry` does so itself.
+    ///
     /// Default: returns an empty tool list.
     fn list_tools(
         &self,
@@ -329,8 +981,132 @@ pub trait Handler {
     ) -> impl Future<Output = Result<ListToolsResult, Error>> {
         std::future::ready(Ok(ListToolsResult::default()))
     }
+    /// Handle a `resources/list` request.
+    ///
+    /// Default: returns an empty resource list.
+    fn list_resources(
+        &self,
+        context: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> impl Future<Output = Result<ListResourcesResult, Error>> {
+        std::future::ready(Ok(ListResourcesResult::default()))
+    }
+    /// Handle a `resources/templates/list` request.
+    ///
+    /// Templates returned here are also used to match `resources/read` URIs; see
+    /// [`Context::resource_template`] and [`Context::uri_params`].
+    ///
+    /// Default: returns an empty resource template list.
+    fn list_resource_templates(
+        &self,
+        context: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> impl Future<Output = Result<ListResourceTemplatesResult, Error>> {
+        std::future::ready(Ok(ListResourceTemplatesResult::default()))
+    }
+    /// Handle a `resources/read` request.
+    ///
+    /// Default: returns `method_not_found`.
+    fn read_resource(
+        &self,
+        context: Context,
+        request: ReadResourceRequestParam,
+    ) -> impl Future<Output = Result<ReadResourceResult, Error>> {
+        std::future::ready(Err(Error::method_not_found::<ReadResourceRequestMethod>()))
+    }
+    /// Handle a `prompts/list` request.
+    ///
+    /// Use [`prompt_arguments_for_type`](crate::prompt_arguments_for_type) to describe arguments.
+    ///
+    /// Default: returns an empty prompt list.
+    fn list_prompts(
+        &self,
+        context: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> impl Future<Output = Result<ListPromptsResult, Error>> {
+        std::future::ready(Ok(ListPromptsResult::default()))
+    }
+    /// Handle a `prompts/get` request.
+    ///
+    /// Default: returns `method_not_found`.
+    fn get_prompt(
+        &self,
+        context: Context,
+        request: GetPromptRequestParam,
+    ) -> impl Future<Output = Result<GetPromptResult, Error>> {
+        std::future::ready(Err(Error::method_not_found::<GetPromptRequestMethod>()))
+    }
+    /// Handle a `completion/complete` request for a prompt or resource template argument.
+    ///
+    /// When overriding this, also return `true` from [`Handler::completions`] so that clients
+    /// learn about it.
+    ///
+    /// Default: returns `method_not_found`.
+    fn complete(
+        &self,
+        context: Context,
+        request: CompleteRequestParam,
+    ) -> impl Future<Output = Result<CompleteResult, Error>> {
+        std::future::ready(Err(Error::method_not_found::<CompleteRequestMethod>()))
+    }
+    /// Whether [`Handler::complete`] is implemented.
+    ///
+    /// When `true`, the `completions` capability is added to the [`Handler::get_info`]
+    /// capabilities on `initialize`, the same way `logging` always is.
+    ///
+    /// Default: `false`.
+    fn completions(&self) -> bool {
+        false
+    }
+    /// Whether `tools/call` arguments are validated against the `input_schema` of the tool
+    /// returned by [`Handler::list_tools`] before [`Handler::call_tool`] runs.
+    ///
+    /// Invalid arguments are rejected with an `invalid_params` error listing the failing JSON
+    /// pointers. Calls to tools missing from `list_tools` are passed through unchecked.
+    ///
+    /// Default: `false`.
+    fn validate_arguments(&self) -> bool {
+        false
+    }
+    /// Whether calls to the tool named `name` may be answered by
+    /// [`Server::handle_query`](crate::Server::handle_query) instead of being upgraded.
+    ///
+    /// The state changes of a query call are discarded, so only opt in tools that read state
+    /// without making inter-canister calls or HTTPS outcalls, which fail in a query. With
+    /// [`init_certification`](crate::init_certification), `http_request_update` certifies the
+    /// result of each call for its exact request, and a query serves it to an identical request
+    /// while the tool still returns the same result; other calls are upgraded.
+    ///
+    /// Default: `false` for every tool.
+    fn query_tool(&self, name: &str) -> bool {
+        false
+    }
+    /// Scope required to call destructive tools (see
+    /// [`ToolExt::is_destructive`](crate::ToolExt::is_destructive)) when serving with
+    /// [`Server::handle_with_oauth`](crate::Server::handle_with_oauth).
+    ///
+    /// Tokens without it get a `403` with an `insufficient_scope` challenge for any `tools/call`
+    /// that is not known, through [`Handler::list_tools`], to be non-destructive, so clients can
+    /// ask the user to confirm and obtain a token with the extra scope. List it in
+    /// [`OAuthConfig::scopes_supported`] as well.
+    ///
+    /// Default: `None`, no extra scope is required.
+    fn destructive_scope(&self) -> Option<&str> {
+        None
+    }
+    /// Paths the server is mounted at and where other requests go; see [`ServerConfig`].
+    ///
+    /// Default: [`ServerConfig::default`], serving `/mcp` and answering other paths with `404`.
+    fn server_config(&self) -> ServerConfig {
+        ServerConfig::default()
+    }
     /// Provide server metadata and advertised capabilities.
     ///
+    /// With [`init_certification`](crate::init_certification) and sessions disabled, the
+    /// `initialize` result is certified, so call
+    /// [`invalidate_certified_responses`](crate::invalidate_certified_responses) from the update
+    /// call that changes it.
+    ///
     /// Default: returns [`ServerInfo::default`]. You typically want to set
     /// `capabilities` (e.g., enable tools) and identify your implementation.
     fn get_info(&self, context: Context) -> ServerInfo {
@@ -342,6 +1118,7 @@ pub trait Handler {
 mod tests {
     use super::*;
     use futures::executor::block_on;
+    use ic_http_certification::{DefaultCelBuilder, CERTIFICATE_EXPRESSION_HEADER_NAME};
 
     #[test]
     fn test_default_handler() {
@@ -366,6 +1143,60 @@ mod tests {
             Ok(ListToolsResult::default())
         );
 
+        assert_eq!(
+            block_on(H {}.list_resources(Context::default(), None)),
+            Ok(ListResourcesResult::default())
+        );
+
+        assert_eq!(
+            block_on(H {}.list_resource_templates(Context::default(), None)),
+            Ok(ListResourceTemplatesResult::default())
+        );
+
+        assert_eq!(
+            block_on(H {}.list_prompts(Context::default(), None)),
+            Ok(ListPromptsResult::default())
+        );
+
+        assert_eq!(
+            block_on(H {}.get_prompt(
+                Context::default(),
+                GetPromptRequestParam {
+                    name: "foo".to_string(),
+                    arguments: None
+                }
+            )),
+            Err(Error::method_not_found::<GetPromptRequestMethod>())
+        );
+
+        assert_eq!(
+            block_on(H {}.complete(
+                Context::default(),
+                CompleteRequestParam {
+                    r#ref: Reference::Prompt(PromptReference {
+                        name: "foo".to_string(),
+                        title: None
+                    }),
+                    argument: ArgumentInfo {
+                        name: "bar".to_string(),
+                        value: "b".to_string()
+                    },
+                    context: None,
+                }
+            )),
+            Err(Error::method_not_found::<CompleteRequestMethod>())
+        );
+
+        assert_eq!(
+            block_on(H {}.read_resource(
+                Context::default(),
+                ReadResourceRequestParam {
+                    uri: "canister://foo".to_string()
+                }
+            )),
+            Err(Error::method_not_found::<ReadResourceRequestMethod>())
+        );
+
         assert_eq!(H {}.get_info(Context::default()), ServerInfo::default());
     }
 
@@ -432,16 +1263,19 @@ mod tests {
         impl Handler for S {}
 
         // no panic
-        block_on(S {}.handle_notification(JsonRpcNotification {
-            jsonrpc: JsonRpcVersion2_0,
-            notification: ClientNotification::InitializedNotification(NotificationNoParam {
-                method: InitializedNotificationMethod,
-                extensions: Extensions::default(),
-            }),
-        }));
+        block_on(S {}.handle_notification(
+            Context::default(),
+            JsonRpcNotification {
+                jsonrpc: JsonRpcVersion2_0,
+                notification: ClientNotification::InitializedNotification(NotificationNoParam {
+                    method: InitializedNotificationMethod,
+                    extensions: Extensions::default(),
+                }),
+            },
+        ));
 
         match block_on(S {}.handle_request(
-            None,
+            Context::default(),
             JsonRpcRequest {
                 jsonrpc: JsonRpcVersion2_0,
                 id: NumberOrString::Number(1),
@@ -453,6 +1287,9 @@ mod tests {
                         client_info: Implementation {
                             name: "foo".to_string(),
                             version: "bar".to_string(),
+                            title: None,
+                            icons: None,
+                            website_url: None,
                         },
                     },
                     extensions: Extensions::new(),
@@ -468,7 +1305,9 @@ mod tests {
                         assert_eq!(
                             ServerInfo {
                                 protocol_version: ProtocolVersion::V_2025_03_26,
-                                capabilities: ServerCapabilities::default(),
+                                capabilities: ServerCapabilities::builder()
+                                    .enable_logging()
+                                    .build(),
                                 server_info: Implementation::from_build_env(),
                                 instructions: None,
                             },
@@ -482,7 +1321,7 @@ mod tests {
         }
 
         match block_on(S {}.handle_request(
-            None,
+            Context::default(),
             JsonRpcRequest {
                 jsonrpc: JsonRpcVersion2_0,
                 id: NumberOrString::Number(1),
@@ -494,6 +1333,9 @@ mod tests {
                         client_info: Implementation {
                             name: "foo".to_string(),
                             version: "bar".to_string(),
+                            title: None,
+                            icons: None,
+                            website_url: None,
                         },
                     },
                     extensions: Extensions::new(),
@@ -509,7 +1351,9 @@ mod tests {
                         assert_eq!(
                             ServerInfo {
                                 protocol_version: protocol_version_2025_06_18(),
-                                capabilities: ServerCapabilities::default(),
+                                capabilities: ServerCapabilities::builder()
+                                    .enable_logging()
+                                    .build(),
                                 server_info: Implementation::from_build_env(),
                                 instructions: None,
                             },
@@ -523,7 +1367,7 @@ mod tests {
         }
 
         match block_on(S {}.handle_request(
-            None,
+            Context::default(),
             JsonRpcRequest {
                 jsonrpc: JsonRpcVersion2_0,
                 id: NumberOrString::Number(1),
@@ -535,6 +1379,9 @@ mod tests {
                         client_info: Implementation {
                             name: "foo".to_string(),
                             version: "bar".to_string(),
+                            title: None,
+                            icons: None,
+                            website_url: None,
                         },
                     },
                     extensions: Extensions::new(),
@@ -550,7 +1397,9 @@ mod tests {
                         assert_eq!(
                             ServerInfo {
                                 protocol_version: protocol_version_2025_06_18(),
-                                capabilities: ServerCapabilities::default(),
+                                capabilities: ServerCapabilities::builder()
+                                    .enable_logging()
+                                    .build(),
                                 server_info: Implementation::from_build_env(),
                                 instructions: None,
                             },
@@ -564,7 +1413,7 @@ mod tests {
         }
 
         match block_on(S {}.handle_request(
-            None,
+            Context::default(),
             JsonRpcRequest {
                 jsonrpc: JsonRpcVersion2_0,
                 id: NumberOrString::Number(1),
@@ -589,7 +1438,7 @@ mod tests {
         }
 
         match block_on(S {}.handle_request(
-            None,
+            Context::default(),
             JsonRpcRequest {
                 jsonrpc: JsonRpcVersion2_0,
                 id: NumberOrString::Number(1),
@@ -611,7 +1460,7 @@ mod tests {
         }
 
         match block_on(S {}.handle_request(
-            None,
+            Context::default(),
             JsonRpcRequest {
                 jsonrpc: JsonRpcVersion2_0,
                 id: NumberOrString::Number(1),
@@ -630,7 +1479,7 @@ mod tests {
         }
 
         match block_on(S {}.handle_request(
-            None,
+            Context::default(),
             JsonRpcRequest {
                 jsonrpc: JsonRpcVersion2_0,
                 id: NumberOrString::Number(1),
@@ -640,18 +1489,253 @@ mod tests {
                     extensions: Extensions::new(),
                 }),
             },
+        )) {
+            JsonRpcMessage::Response(res) => {
+                assert_eq!(res.jsonrpc, JsonRpcVersion2_0 {});
+                assert_eq!(res.id, NumberOrString::Number(1));
+            }
+            _ => panic!("Expected JsonRpcMessage::Response"),
+        }
+
+        match block_on(S {}.handle_request(
+            Context::default(),
+            JsonRpcRequest {
+                jsonrpc: JsonRpcVersion2_0,
+                id: NumberOrString::Number(1),
+                request: ClientRequest::ReadResourceRequest(Request {
+                    method: ReadResourceRequestMethod,
+                    params: ReadResourceRequestParam {
+                        uri: "canister://foo".to_string(),
+                    },
+                    extensions: Extensions::new(),
+                }),
+            },
+        )) {
+            JsonRpcMessage::Error(error) => {
+                assert_eq!(error.jsonrpc, JsonRpcVersion2_0 {});
+                assert_eq!(error.id, NumberOrString::Number(1));
+            }
+            _ => panic!("Expected JsonRpcMessage::Error"),
+        }
+
+        match block_on(S {}.handle_request(
+            Context::default(),
+            JsonRpcRequest {
+                jsonrpc: JsonRpcVersion2_0,
+                id: NumberOrString::Number(1),
+                request: ClientRequest::SubscribeRequest(Request {
+                    method: SubscribeRequestMethod,
+                    params: SubscribeRequestParam {
+                        uri: "canister://foo".to_string(),
+                    },
+                    extensions: Extensions::new(),
+                }),
+            },
         )) {
             JsonRpcMessage::Error(error) => {
                 assert_eq!(error.jsonrpc, JsonRpcVersion2_0 {});
                 assert_eq!(error.id, NumberOrString::Number(1));
 
-                assert_eq!(
-                    error.error,
-                    Error::new(ErrorCode::METHOD_NOT_FOUND, "Method not found", None,)
-                )
+                assert_eq!(
+                    error.error,
+                    Error::new(ErrorCode::METHOD_NOT_FOUND, "Method not found", None,)
+                )
+            }
+            _ => panic!("Expected JsonRpcMessage::Error"),
+        }
+    }
+
+    #[test]
+    fn test_service_completion() {
+        struct C;
+        impl Handler for C {
+            fn completions(&self) -> bool {
+                true
+            }
+
+            async fn complete(
+                &self,
+                _: Context,
+                request: CompleteRequestParam,
+            ) -> Result<CompleteResult, Error> {
+                Ok(CompleteResult {
+                    completion: CompletionInfo {
+                        values: ["ICP", "ckBTC"]
+                            .into_iter()
+                            .filter(|v| v.starts_with(&request.argument.value))
+                            .map(str::to_string)
+                            .collect(),
+                        total: None,
+                        has_more: Some(false),
+                    },
+                })
+            }
+        }
+
+        struct S;
+        impl Handler for S {}
+
+        let initialize = || JsonRpcRequest {
+            jsonrpc: JsonRpcVersion2_0,
+            id: NumberOrString::Number(1),
+            request: ClientRequest::InitializeRequest(Request {
+                method: InitializeResultMethod,
+                params: InitializeRequestParam {
+                    protocol_version: ProtocolVersion::V_2025_03_26,
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation {
+                        name: "foo".to_string(),
+                        version: "bar".to_string(),
+                        title: None,
+                        icons: None,
+                        website_url: None,
+                    },
+                },
+                extensions: Extensions::new(),
+            }),
+        };
+
+        match block_on(C {}.handle_request(Context::default(), initialize())) {
+            JsonRpcMessage::Response(JsonRpcResponse {
+                result: ServerResult::InitializeResult(info),
+                ..
+            }) => assert_eq!(info.capabilities.completions, Some(JsonObject::new())),
+            _ => panic!("Expected ServerResult::InitializeResult"),
+        }
+
+        match block_on(S {}.handle_request(Context::default(), initialize())) {
+            JsonRpcMessage::Response(JsonRpcResponse {
+                result: ServerResult::InitializeResult(info),
+                ..
+            }) => assert_eq!(info.capabilities.completions, None),
+            _ => panic!("Expected ServerResult::InitializeResult"),
+        }
+
+        match block_on(C {}.handle_request(
+            Context::default(),
+            JsonRpcRequest {
+                jsonrpc: JsonRpcVersion2_0,
+                id: NumberOrString::Number(1),
+                request: ClientRequest::CompleteRequest(Request {
+                    method: CompleteRequestMethod,
+                    params: CompleteRequestParam {
+                        r#ref: Reference::Resource(ResourceReference {
+                            uri: "canister://tokens/{symbol}".to_string(),
+                        }),
+                        argument: ArgumentInfo {
+                            name: "symbol".to_string(),
+                            value: "ck".to_string(),
+                        },
+                        context: None,
+                    },
+                    extensions: Extensions::new(),
+                }),
+            },
+        )) {
+            JsonRpcMessage::Response(JsonRpcResponse {
+                result: ServerResult::CompleteResult(result),
+                ..
+            }) => assert_eq!(result.completion.values, vec!["ckBTC".to_string()]),
+            _ => panic!("Expected ServerResult::CompleteResult"),
+        }
+    }
+
+    #[test]
+    fn test_service_match_template() {
+        struct T;
+        impl Handler for T {
+            async fn list_resource_templates(
+                &self,
+                _: Context,
+                request: Option<PaginatedRequestParam>,
+            ) -> Result<ListResourceTemplatesResult, Error> {
+                let template = |uri: &str| {
+                    RawResourceTemplate {
+                        uri_template: uri.to_string(),
+                        name: uri.to_string(),
+                        title: None,
+                        description: None,
+                        mime_type: None,
+                    }
+                    .no_annotation()
+                };
+
+                match request.and_then(|r| r.cursor).as_deref() {
+                    None => Ok(ListResourceTemplatesResult {
+                        next_cursor: Some("2".to_string()),
+                        resource_templates: vec![template("canister://blocks/{height}")],
+                    }),
+                    Some("2") => Ok(ListResourceTemplatesResult {
+                        next_cursor: None,
+                        resource_templates: vec![template("canister://accounts/{principal}")],
+                    }),
+                    Some(_) => Err(Error::invalid_params("invalid cursor", None)),
+                }
+            }
+        }
+
+        let context = block_on(T {}.match_template(Context::default(), "canister://blocks/12"));
+        assert_eq!(
+            context.resource_template.as_deref(),
+            Some("canister://blocks/{height}")
+        );
+        assert_eq!(context.uri_params.parse::<u64>("height"), Ok(12));
+
+        let context =
+            block_on(T {}.match_template(Context::default(), "canister://accounts/aaaaa-aa"));
+        assert_eq!(
+            context.resource_template.as_deref(),
+            Some("canister://accounts/{principal}")
+        );
+        assert_eq!(context.uri_params.get("principal"), Some("aaaaa-aa"));
+
+        assert_eq!(
+            block_on(T {}.match_template(Context::default(), "canister://other")),
+            Context::default()
+        );
+    }
+
+    #[test]
+    fn test_service_find_tool_paging() {
+        use std::cell::Cell;
+
+        struct Endless {
+            repeat: bool,
+            pages: Cell<usize>,
+        }
+        impl Handler for Endless {
+            async fn list_tools(
+                &self,
+                _: Context,
+                _: Option<PaginatedRequestParam>,
+            ) -> Result<ListToolsResult, Error> {
+                self.pages.set(self.pages.get() + 1);
+                Ok(ListToolsResult {
+                    next_cursor: Some(match self.repeat {
+                        true => "again".to_string(),
+                        false => self.pages.get().to_string(),
+                    }),
+                    tools: vec![],
+                })
             }
-            _ => panic!("Expected JsonRpcMessage::Error"),
         }
+
+        let endless = Endless {
+            repeat: false,
+            pages: Cell::new(0),
+        };
+        assert_eq!(block_on(endless.find_tool(Context::default(), "foo")), None);
+        assert_eq!(endless.pages.get(), MAX_PAGES);
+
+        let repeating = Endless {
+            repeat: true,
+            pages: Cell::new(0),
+        };
+        assert_eq!(
+            block_on(repeating.find_tool(Context::default(), "foo")),
+            None
+        );
+        assert_eq!(repeating.pages.get(), 2);
     }
 
     #[test]
@@ -682,7 +1766,21 @@ mod tests {
         );
 
         assert_eq!(
-            block_on(A{}.raw_handle(None, &HttpRequest::builder().with_method(Method::POST).with_url("/foo/mcp").with_body(b"{").build())),
+            block_on(
+                A {}.raw_handle(
+                    None,
+                    &HttpRequest::builder()
+                        .with_method(Method::POST)
+                        .with_url("/foo/mcp")
+                        .build()
+                )
+            )
+            .status_code(),
+            StatusCode::from_u16(404).unwrap()
+        );
+
+        assert_eq!(
+            block_on(A{}.raw_handle(None, &HttpRequest::builder().with_method(Method::POST).with_url("/mcp?session=1").with_body(b"{").build())),
             HttpResponse::builder()
                 .with_status_code(StatusCode::from_u16(200).unwrap())
                 .with_headers(vec![("Content-Type".to_string(), "application/json".to_string())])
@@ -943,6 +2041,165 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_called_tools() {
+        assert_eq!(
+            called_tools(
+                br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"transfer"}}"#
+            ),
+            vec!["transfer".to_string()]
+        );
+        assert_eq!(
+            called_tools(
+                br#"[{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"a"}},{"jsonrpc":"2.0","id":2,"method":"tools/list"},{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"b"}}]"#
+            ),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert_eq!(
+            called_tools(br#"{"jsonrpc":"2.0","id":1,"method":"tools/call"}"#),
+            vec![String::new()]
+        );
+        assert!(called_tools(b"{").is_empty());
+    }
+
+    #[test]
+    fn test_supports_structured() {
+        let older = ProtocolVersion::V_2025_03_26;
+        let newer = protocol_version_2025_06_18();
+        assert!(!supports_structured(&Context::default(), None));
+        assert!(!supports_structured(&Context::default(), Some(&older)));
+        assert!(supports_structured(&Context::default(), Some(&newer)));
+
+        let negotiated = |version: &ProtocolVersion| Context {
+            protocol_version: Some(version.clone()),
+            ..Default::default()
+        };
+        assert!(supports_structured(&negotiated(&newer), None));
+        assert!(!supports_structured(&negotiated(&older), Some(&newer)));
+    }
+
+    #[test]
+    fn test_accept() {
+        let accept_header = |value: &str| {
+            accept(
+                &HttpRequest::builder()
+                    .with_headers(vec![("accept".to_string(), value.to_string())])
+                    .build(),
+            )
+        };
+
+        assert_eq!(
+            accept(&HttpRequest::builder().build()),
+            Accept {
+                json: true,
+                event_stream: false
+            }
+        );
+        assert_eq!(
+            accept_header("application/json, text/event-stream"),
+            Accept {
+                json: true,
+                event_stream: true
+            }
+        );
+        assert_eq!(
+            accept_header("text/event-stream"),
+            Accept {
+                json: false,
+                event_stream: true
+            }
+        );
+        assert_eq!(
+            accept_header("application/json, text/event-stream;q=0"),
+            Accept {
+                json: true,
+                event_stream: false
+            }
+        );
+        assert_eq!(
+            accept_header("*/*"),
+            Accept {
+                json: true,
+                event_stream: false
+            }
+        );
+        assert_eq!(
+            accept_header("TEXT/*; q=0.5"),
+            Accept {
+                json: false,
+                event_stream: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_reply() {
+        let request = |value: &str| {
+            HttpRequest::builder()
+                .with_headers(vec![("Accept".to_string(), value.to_string())])
+                .build()
+        };
+        let json = HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string(),
+            )])
+            .with_body(br#"{"id":1,"jsonrpc":"2.0","result":{}}"#)
+            .build();
+        let notification = || json!({"jsonrpc": "2.0", "method": "notifications/message"});
+        let result = || json!({"jsonrpc": "2.0", "id": 1, "result": {}});
+
+        assert_eq!(
+            reply(
+                &request("application/json, text/event-stream"),
+                vec![notification()],
+                result()
+            ),
+            HttpResponse::builder()
+                .with_status_code(StatusCode::from_u16(200).unwrap())
+                .with_headers(vec![(
+                    "Content-Type".to_string(),
+                    "text/event-stream".to_string()
+                )])
+                .with_body(
+                    br#"event: message
+data: {"jsonrpc":"2.0","method":"notifications/message"}
+
+event: message
+data: {"id":1,"jsonrpc":"2.0","result":{}}
+
+"#
+                )
+                .build()
+        );
+        assert_eq!(
+            reply(
+                &request("application/json, text/event-stream"),
+                vec![],
+                result()
+            ),
+            json
+        );
+        assert_eq!(
+            reply(&request("application/json"), vec![notification()], result()),
+            json
+        );
+        assert_eq!(
+            reply(&request("text/event-stream"), vec![], result()),
+            HttpResponse::builder()
+                .with_status_code(StatusCode::from_u16(200).unwrap())
+                .with_headers(vec![(
+                    "Content-Type".to_string(),
+                    "text/event-stream".to_string()
+                )])
+                .with_body(
+                    b"event: message\ndata: {\"id\":1,\"jsonrpc\":\"2.0\",\"result\":{}}\n\n"
+                )
+                .build()
+        );
+    }
+
     #[test]
     fn test_server_handle() {
         struct A;
@@ -967,6 +2224,477 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_server_handle_query() {
+        use crate::init_certification;
+        use ic_http_certification::Method;
+
+        struct A;
+        impl Handler for A {
+            async fn list_tools(
+                &self,
+                _: Context,
+                _: Option<PaginatedRequestParam>,
+            ) -> Result<ListToolsResult, Error> {
+                Ok(ListToolsResult {
+                    next_cursor: None,
+                    tools: vec![
+                        Tool::new("balance", "Get the balance", JsonObject::new()).read_only(true),
+                        Tool::new("history", "List transfers", JsonObject::new()).read_only(true),
+                        Tool::new("transfer", "Transfer tokens", JsonObject::new()),
+                    ],
+                })
+            }
+
+            async fn call_tool(
+                &self,
+                _: Context,
+                _: CallToolRequestParam,
+            ) -> Result<CallToolResult, Error> {
+                Ok(CallToolResult::success(vec![Content::text("42")]))
+            }
+
+            fn query_tool(&self, name: &str) -> bool {
+                name == "balance"
+            }
+        }
+
+        let request = |body: &str| {
+            HttpRequest::builder()
+                .with_method(Method::POST)
+                .with_url("/mcp")
+                .with_body(body.as_bytes().to_vec())
+                .build()
+        };
+        let query = |body: &str| block_on(A {}.handle_query(&request(body), |_| true));
+        let balance =
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"balance"}}"#;
+
+        // Uncertified responses would be rejected by the gateway, rejections included.
+        assert_eq!(query(balance), upgrade());
+        assert_eq!(
+            query(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#),
+            upgrade()
+        );
+        assert_eq!(
+            block_on(
+                A {}.handle_query(&HttpRequest::builder().with_url("/mcp").build(), |_| false)
+            ),
+            upgrade()
+        );
+
+        // Query tool results are served once an update call has certified them for the request.
+        init_certification(&A);
+        assert_eq!(query(balance), upgrade());
+        let computed = block_on(A {}.handle(&request(balance), |_| true));
+        let res = query(balance);
+        assert_eq!(res.upgrade(), None);
+        assert_eq!(res.body(), computed.body());
+        assert!(String::from_utf8(res.body().to_vec())
+            .unwrap()
+            .contains(r#""text":"42""#));
+        assert!(res.headers().iter().any(|(key, value)| {
+            key == CERTIFICATE_EXPRESSION_HEADER_NAME
+                && *value != DefaultCelBuilder::skip_certification().to_string()
+        }));
+        assert!(res.headers().iter().any(|(key, _)| key == "IC-Certificate"));
+        assert_eq!(
+            query(r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"balance"}}"#),
+            upgrade()
+        );
+
+        // Read-only hints alone do not opt a tool in.
+        for body in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"history"}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"transfer"}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"missing"}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"logging/setLevel","params":{"level":"debug"}}"#,
+            r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},{"jsonrpc":"2.0","id":2,"method":"resources/read","params":{"uri":"a://b"}}]"#,
+            &format!(r#"[{{"jsonrpc":"2.0","id":0,"method":"ping"}},{balance}]"#),
+            "{",
+        ] {
+            block_on(A {}.handle(&request(body), |_| true));
+            assert_eq!(query(body), upgrade());
+        }
+    }
+
+    #[test]
+    fn test_server_config() {
+        use ic_http_certification::Method;
+
+        struct A;
+        impl Handler for A {
+            fn server_config(&self) -> ServerConfig {
+                ServerConfig {
+                    paths: vec!["/mcp".to_string(), "/api/v1/mcp".to_string()],
+                    fallthrough: Some(|_| {
+                        HttpResponse::builder()
+                            .with_status_code(StatusCode::from_u16(418).unwrap())
+                            .build()
+                    }),
+                    ..Default::default()
+                }
+            }
+        }
+
+        let ping = |url: &str| {
+            HttpRequest::builder()
+                .with_method(Method::POST)
+                .with_url(url)
+                .with_body(br#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#)
+                .build()
+        };
+        let status = |res: HttpResponse| res.status_code().as_u16();
+
+        assert_eq!(status(block_on(A {}.handle(&ping("/mcp"), |_| true))), 200);
+        assert_eq!(
+            status(block_on(A {}.handle(&ping("/api/v1/mcp?x=1"), |_| true))),
+            200
+        );
+        assert_eq!(status(block_on(A {}.handle(&ping("/mcp"), |_| false))), 401);
+        for url in ["/foo/mcp", "/mcp/", "/api/v1"] {
+            assert_eq!(status(block_on(A {}.handle(&ping(url), |_| false))), 418);
+            assert_eq!(
+                block_on(A {}.handle_query(&ping(url), |_| false)),
+                upgrade()
+            );
+        }
+
+        assert!(ServerConfig::default().matches(&ping("/mcp?a=b")));
+        assert!(!ServerConfig::default().matches(&ping("/v1/mcp")));
+        assert_eq!(
+            block_on(
+                crate::Compose::new()
+                    .raw_handle(None, &HttpRequest::builder().with_url("/mcp").build())
+            )
+            .body(),
+            b"Not Found or Method Not Allowed. Use POST to /mcp"
+        );
+        assert_eq!(
+            block_on(
+                crate::Compose::new()
+                    .with_config(ServerConfig {
+                        paths: vec!["/a".to_string(), "/b".to_string()],
+                        ..Default::default()
+                    })
+                    .raw_handle(None, &HttpRequest::builder().with_url("/a").build())
+            )
+            .body(),
+            b"Not Found or Method Not Allowed. Use POST to /a or /b"
+        );
+    }
+
+    #[test]
+    fn test_server_cors() {
+        use crate::{init_certification, CorsPolicy};
+        use ic_http_certification::Method;
+
+        struct A;
+        impl Handler for A {
+            fn server_config(&self) -> ServerConfig {
+                ServerConfig {
+                    cors: Some(CorsPolicy {
+                        allowed_origins: vec!["https://inspector.example.com".to_string()],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }
+            }
+        }
+
+        let request = |method: Method, origin: &str| {
+            HttpRequest::builder()
+                .with_method(method)
+                .with_url("/mcp")
+                .with_headers(vec![("Origin".to_string(), origin.to_string())])
+                .with_body(br#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#)
+                .build()
+        };
+        let header = |res: &HttpResponse, name: &str| {
+            res.headers()
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let allowed = "https://inspector.example.com";
+
+        // Preflights need no authorization and are answered from the query once certified.
+        let preflight = request(Method::OPTIONS, allowed);
+        assert_eq!(
+            block_on(A {}.handle_query(&preflight, |_| false)).upgrade(),
+            Some(true)
+        );
+        init_certification(&A);
+        for res in [
+            block_on(A {}.handle(&preflight, |_| false)),
+            block_on(A {}.handle_query(&preflight, |_| false)),
+        ] {
+            assert_eq!(res.status_code().as_u16(), 204);
+            assert_eq!(res.upgrade(), None);
+            assert_eq!(
+                header(&res, "Access-Control-Allow-Origin").as_deref(),
+                Some(allowed)
+            );
+            assert_eq!(
+                header(&res, "Access-Control-Max-Age").as_deref(),
+                Some("86400")
+            );
+            assert!(header(&res, "Access-Control-Allow-Headers")
+                .unwrap()
+                .contains("Mcp-Session-Id"));
+        }
+        // Gateways reject uncertified query responses, so preflights are certified too, for
+        // their origin.
+        let res = block_on(A {}.handle_query(&preflight, |_| false));
+        assert!(header(&res, "IC-Certificate").is_some());
+        assert_ne!(
+            header(&res, CERTIFICATE_EXPRESSION_HEADER_NAME),
+            Some(DefaultCelBuilder::skip_certification().to_string())
+        );
+        let other = request(Method::OPTIONS, "https://other.example.com");
+        assert_eq!(block_on(A {}.handle_query(&other, |_| false)), upgrade());
+        let res = block_on(A {}.handle(
+            &request(Method::OPTIONS, "https://evil.example.com"),
+            |_| true,
+        ));
+        assert_eq!(res.status_code().as_u16(), 204);
+        assert_eq!(header(&res, "Access-Control-Allow-Origin"), None);
+
+        let res = block_on(A {}.handle(&request(Method::POST, allowed), |_| true));
+        assert_eq!(res.status_code().as_u16(), 200);
+        assert_eq!(
+            header(&res, "Access-Control-Allow-Origin").as_deref(),
+            Some(allowed)
+        );
+        assert!(header(&res, "Access-Control-Expose-Headers")
+            .unwrap()
+            .contains("Mcp-Session-Id"));
+
+        let res = block_on(A {}.handle_with_oauth(
+            &request(Method::POST, allowed),
+            OAuthConfig {
+                metadata_url:
+                    "https://my-server.com/.well-known/oauth-protected-resource".to_string(),
+                ..Default::default()
+            },
+        ));
+        assert_eq!(res.status_code().as_u16(), 401);
+        assert_eq!(
+            header(&res, "Access-Control-Allow-Origin").as_deref(),
+            Some(allowed)
+        );
+
+        assert!(CorsPolicy::default().allows("https://any.example.com"));
+    }
+
+    #[test]
+    fn test_server_origins() {
+        use crate::{CorsPolicy, OriginPolicy};
+        use ic_http_certification::Method;
+
+        struct A {
+            allow_missing: bool,
+        }
+        impl Handler for A {
+            fn server_config(&self) -> ServerConfig {
+                ServerConfig {
+                    origins: Some(OriginPolicy {
+                        allowed_origins: vec!["https://app.example.com".to_string()],
+                        allow_missing: self.allow_missing,
+                    }),
+                    ..Default::default()
+                }
+            }
+        }
+
+        let request = |origin: Option<&str>| {
+            HttpRequest::builder()
+                .with_method(Method::POST)
+                .with_url("/mcp")
+                .with_headers(
+                    origin
+                        .map(|origin| ("Origin".to_string(), origin.to_string()))
+                        .into_iter()
+                        .collect(),
+                )
+                .with_body(br#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#)
+                .build()
+        };
+        let status = |res: HttpResponse| res.status_code().as_u16();
+        let server = A {
+            allow_missing: true,
+        };
+
+        assert_eq!(
+            status(block_on(
+                server.handle(&request(Some("https://app.example.com")), |_| true)
+            )),
+            200
+        );
+        assert_eq!(
+            status(block_on(server.handle(&request(None), |_| true))),
+            200
+        );
+        assert_eq!(
+            block_on(server.handle(&request(Some("https://evil.example.com")), |_| true)),
+            HttpResponse::builder()
+                .with_status_code(StatusCode::from_u16(403).unwrap())
+                .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+                .with_body(br#"Origin not allowed"#)
+                .build()
+        );
+        assert_eq!(
+            block_on(server.handle_query(&request(Some("http://localhost:8080")), |_| true)),
+            upgrade()
+        );
+        assert_eq!(
+            status(block_on(server.handle_with_oauth(
+                &request(Some("https://evil.example.com")),
+                OAuthConfig {
+                    metadata_url:
+                        "https://my-server.com/.well-known/oauth-protected-resource".to_string(),
+                    ..Default::default()
+                },
+            ))),
+            403
+        );
+        let metadata = |origin: &str| {
+            HttpRequest::builder()
+                .with_method(Method::GET)
+                .with_url("/.well-known/oauth-protected-resource")
+                .with_headers(vec![("Origin".to_string(), origin.to_string())])
+                .build()
+        };
+        let oauth = || OAuthConfig {
+            metadata_url: "https://my-server.com/.well-known/oauth-protected-resource".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            status(block_on(server.handle_with_oauth(
+                &metadata("https://app.example.com"),
+                oauth()
+            ))),
+            200
+        );
+        assert_eq!(
+            status(block_on(server.handle_with_oauth(
+                &metadata("https://evil.example.com"),
+                oauth()
+            ))),
+            403
+        );
+
+        let strict = A {
+            allow_missing: false,
+        };
+        assert_eq!(
+            status(block_on(strict.handle(&request(None), |_| true))),
+            403
+        );
+
+        // The default CORS policy allows `*`, but only origins allowed above get CORS headers.
+        struct B;
+        impl Handler for B {
+            fn server_config(&self) -> ServerConfig {
+                ServerConfig {
+                    cors: Some(CorsPolicy::default()),
+                    ..A {
+                        allow_missing: true,
+                    }
+                    .server_config()
+                }
+            }
+        }
+        let allow_origin = |res: HttpResponse| {
+            res.headers()
+                .iter()
+                .find(|(key, _)| key == "Access-Control-Allow-Origin")
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(
+            allow_origin(block_on(
+                B.handle(&request(Some("https://app.example.com")), |_| true)
+            ))
+            .as_deref(),
+            Some("https://app.example.com")
+        );
+        let res = block_on(B.handle(&request(Some("https://evil.example.com")), |_| true));
+        assert_eq!(res.status_code().as_u16(), 403);
+        assert_eq!(allow_origin(res), None);
+    }
+
+    #[test]
+    fn test_server_certified_query() {
+        use crate::{init_certification, invalidate_certified_responses};
+        use ic_http_certification::Method;
+
+        struct A;
+        impl Handler for A {}
+
+        init_certification(&A);
+        let request = |body: &str| {
+            HttpRequest::builder()
+                .with_method(Method::POST)
+                .with_url("/mcp")
+                .with_body(body.as_bytes().to_vec())
+                .build()
+        };
+        let certified = |res: &HttpResponse| {
+            res.headers()
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case("IC-Certificate"))
+        };
+        let list = request(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#);
+
+        // Initialization certifies the tool list up front.
+        let res = block_on(A {}.handle(&list, |_| true));
+        let served = block_on(A {}.handle_query(&list, |_| true));
+        assert!(certified(&served));
+        assert_eq!(served.body(), res.body());
+
+        // Only certifiable requests are served, and only once certified.
+        let prompts = request(r#"{"jsonrpc":"2.0","id":1,"method":"prompts/list"}"#);
+        assert_eq!(block_on(A {}.handle_query(&prompts, |_| true)), upgrade());
+        let other = request(r#"{"jsonrpc":"2.0","id":"a","method":"tools/list"}"#);
+        assert_eq!(block_on(A {}.handle_query(&other, |_| true)), upgrade());
+        block_on(A {}.handle(&other, |_| true));
+        assert!(certified(&block_on(A {}.handle_query(&other, |_| true))));
+        assert_eq!(block_on(A {}.handle_query(&list, |_| false)), upgrade());
+
+        // Responses to other requests, fallthrough routes included, are never certified; those
+        // carrying their own certificate are served as they are.
+        struct B;
+        impl Handler for B {
+            fn server_config(&self) -> ServerConfig {
+                ServerConfig {
+                    fallthrough: Some(|req| {
+                        let mut res = HttpResponse::builder().with_body(b"<html>").build();
+                        if req.url() == "/certified.html" {
+                            res.add_header(("IC-Certificate".to_string(), String::new()));
+                        }
+                        res
+                    }),
+                    ..Default::default()
+                }
+            }
+        }
+        for req in [
+            HttpRequest::get("/mcp").build(),
+            HttpRequest::get("/index.html").build(),
+        ] {
+            block_on(B {}.handle(&req, |_| true));
+            assert_eq!(block_on(B {}.handle_query(&req, |_| true)), upgrade());
+        }
+        let asset = HttpRequest::get("/certified.html").build();
+        assert_eq!(
+            block_on(B {}.handle_query(&asset, |_| true)).body(),
+            b"<html>"
+        );
+
+        invalidate_certified_responses();
+        assert_eq!(block_on(A {}.handle_query(&list, |_| true)), upgrade());
+    }
+
     #[test]
     fn test_server_handle_with_oauth() {
         use crate::IssuerConfig;
@@ -1040,4 +2768,22 @@ mod tests {
                 .build()
         );
     }
+
+    #[test]
+    fn test_response_too_large() {
+        let res = response(json!("x".repeat(MAX_RESPONSE_BYTES)));
+        assert_eq!(
+            from_slice::<Value>(res.body()).unwrap(),
+            json!({"jsonrpc": "2.0", "error": {"code": -32603, "message": format!("Internal error: response of {} bytes exceeds the {MAX_RESPONSE_BYTES} bytes limit", MAX_RESPONSE_BYTES + 2)}})
+        );
+
+        let res = sse_response(
+            vec![json!("x".repeat(MAX_RESPONSE_BYTES / 2))],
+            json!("x".repeat(MAX_RESPONSE_BYTES / 2)),
+        );
+        assert_eq!(
+            from_slice::<Value>(res.body()).unwrap()["error"]["code"],
+            json!(-32603)
+        );
+    }
 }
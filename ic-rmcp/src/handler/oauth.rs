@@ -29,6 +29,9 @@ pub struct OAuthConfig {
 pub struct Claims {
     /// Subject identifier of the authenticated principal.
     pub sub: String,
+    /// Space-separated scopes granted to the token, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
     iss: String,
     aud: String,
     exp: u64,
@@ -82,6 +85,15 @@ pub fn validate_token(
     Ok(token_data.claims)
 }
 
+impl Claims {
+    /// Whether the token was granted `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|s| s == scope))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,7 @@
 //! ic-rmcp: A lightweight Rust SDK for building Model Context Protocol (MCP) servers on the Internet Computer (IC).
 //!
-//! This crate focuses on the core MCP tools capability over the IC Streamable HTTP transport.
+//! This crate focuses on the core MCP tools, resources and prompts capabilities over the IC
+//! Streamable HTTP transport.
 //!
 //! Quick start:
 //! - Implement the [`Handler`] trait for your server logic
@@ -15,10 +16,69 @@ pub use handler::{Context, Handler};
 
 mod server;
 /// Entry points for handling Streamable HTTP requests to your MCP server.
-pub use server::Server;
+pub use server::{CorsPolicy, OriginPolicy, Server, ServerConfig};
 
 mod state;
 
+mod logging;
+
+mod progress;
+
+mod session;
+/// Optional `Mcp-Session-Id` sessions kept in stable memory.
+pub use session::{init_sessions, SessionConfig};
+
+mod certification;
+/// Certified query responses for `initialize`, `tools/list` and the OAuth metadata document.
+pub use certification::{init_certification, invalidate_certified_responses};
+
+mod structured;
+/// Structured tool output for `2025-06-18` clients.
+pub use structured::structured_result;
+
+mod tool;
+/// Builder methods for tool annotations, titles and output schemas.
+pub use tool::ToolExt;
+
+mod content;
+/// Tool result content helpers with response size checks.
+pub use content::{
+    audio_content, check_size, embedded_blob, embedded_text, image_content, json_content,
+    pretty_json_content, resource_link, MAX_RESPONSE_BYTES,
+};
+
+mod router;
+/// Typed tool routing generated by the `#[tool_router]` and `#[tool_handler]` macros.
+pub use ic_rmcp_macros::{tool, tool_handler, tool_router};
+/// Helpers used by generated tool routers, also handy in hand-written `call_tool`s.
+pub use router::{parse_arguments, tool_not_found, ToolRouter};
+
+mod error;
+/// Tool failures reported as `isError` results, separate from JSON-RPC errors.
+pub use error::{IntoToolResult, ToolError};
+
+mod validate;
+
+mod registry;
+/// Tools registered at runtime with per-tool async handlers.
+pub use registry::ToolRegistry;
+
+mod compose;
+/// Combinator serving several handlers from one server under name prefixes.
+pub use compose::Compose;
+
+mod pagination;
+/// Pagination of list results with tamper-evident cursors.
+pub use pagination::{Page, Paginator};
+
+mod prompt;
+/// Helper to describe prompt arguments with a Rust type.
+pub use prompt::prompt_arguments_for_type;
+
+mod resource;
+/// URI template matching for resource templates.
+pub use resource::{match_resource_template, UriParams, UriTemplate};
+
 /// OAuth configuration types for protecting your MCP server and advertising metadata.
 pub use handler::oauth::{IssuerConfig, OAuthConfig};
 /// Helper to generate a JSON Schema for a Rust type to describe tool parameters.
@@ -26,4 +86,4 @@ pub use rmcp::handler::server::tool::schema_for_type;
 /// Re-export of MCP model types (requests, responses, capabilities, etc.).
 pub use rmcp::model;
 /// Common error type returned by handler methods.
-pub use rmcp::Error;
+pub use rmcp::ErrorData as Error;
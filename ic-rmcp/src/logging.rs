@@ -0,0 +1,117 @@
+//! Server log messages sent to the client as `notifications/message`.
+//!
+//! Messages are recorded on the request [`Context`] and delivered with the response when the
+//! client accepts `text/event-stream`. Every message is also written to the canister log, so
+//! controllers can read it with `dfx canister logs`.
+//!
+//! The level a client sets with `logging/setLevel` is stored in its session. Without
+//! [`init_sessions`](crate::init_sessions) there is nowhere to keep it: the request is
+//! acknowledged, and every client keeps receiving `info` and above.
+use crate::handler::Context;
+use crate::model::{
+    JsonRpcNotification, JsonRpcVersion2_0, LoggingLevel, LoggingMessageNotification,
+    LoggingMessageNotificationParam,
+};
+use serde::Serialize;
+use serde_json::{to_value, Value};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Notifications emitted by a handler while processing a request, shared by all clones of a
+/// [`Context`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Notifications(Rc<RefCell<Vec<Value>>>);
+
+impl Notifications {
+    pub(crate) fn push<N: Serialize>(&self, notification: JsonRpcNotification<N>) {
+        match to_value(notification) {
+            Ok(value) => self.0.borrow_mut().push(value),
+            Err(err) => ic_cdk::eprintln!("Serialize notification: {}", err),
+        }
+    }
+
+    pub(crate) fn take(&self) -> Vec<Value> {
+        self.0.take()
+    }
+}
+
+impl Context {
+    /// Send a log message to the client.
+    ///
+    /// The message is delivered only if `level` is at or above the level the client set for its
+    /// session with `logging/setLevel` (`info` until then). It is always mirrored to the
+    /// canister log.
+    pub fn log(&self, level: LoggingLevel, data: impl Into<Value>) {
+        let data = data.into();
+        ic_cdk::println!("[{}] {}", level_name(level), data);
+
+        if severity(level) >= severity(self.log_level.unwrap_or(LoggingLevel::Info)) {
+            self.notifications.push(JsonRpcNotification {
+                jsonrpc: JsonRpcVersion2_0,
+                notification: LoggingMessageNotification::new(LoggingMessageNotificationParam {
+                    level,
+                    logger: None,
+                    data,
+                }),
+            });
+        }
+    }
+}
+
+fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+fn level_name(level: LoggingLevel) -> String {
+    to_value(level)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_log() {
+        let context = Context::default();
+
+        context.log(LoggingLevel::Debug, "skipped");
+        context
+            .clone()
+            .log(LoggingLevel::Warning, json!({"retry": 1}));
+        assert_eq!(
+            context.notifications.take(),
+            vec![json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/message",
+                "params": {"level": "warning", "data": {"retry": 1}}
+            })]
+        );
+        assert_eq!(context.notifications.take(), Vec::<Value>::new());
+
+        let context = Context {
+            log_level: Some(LoggingLevel::Debug),
+            ..Default::default()
+        };
+        context.log(LoggingLevel::Debug, "kept");
+        assert_eq!(context.notifications.take().len(), 1);
+    }
+
+    #[test]
+    fn test_level_name() {
+        assert_eq!(level_name(LoggingLevel::Emergency), "emergency");
+        assert!(severity(LoggingLevel::Alert) > severity(LoggingLevel::Critical));
+    }
+}
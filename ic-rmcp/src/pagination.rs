@@ -0,0 +1,181 @@
+//! Cursor-based pagination for `tools/list`, `resources/list`, `prompts/list` and friends.
+//!
+//! Cursors encode the offset of the next page followed by an HMAC-SHA256 tag keyed with a
+//! canister secret, so clients cannot forge or edit them. Offsets are positional: if the
+//! collection changes between requests, a page may repeat or skip items.
+use crate::{model::PaginatedRequestParam, Error};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Bytes of the HMAC tag kept in a cursor.
+const TAG_LEN: usize = 16;
+
+/// One page of an ordered collection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the following page, or `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Splits ordered collections into pages with tamper-evident cursors.
+///
+/// The secret should be random and kept across upgrades (for example from `raw_rand` in `init`,
+/// saved in stable memory); changing it invalidates outstanding cursors.
+///
+/// ```ignore
+/// async fn list_tools(
+///     &self,
+///     _: Context,
+///     request: Option<PaginatedRequestParam>,
+/// ) -> Result<ListToolsResult, Error> {
+///     let page = PAGINATOR.with(|p| p.paginate(all_tools(), request))?;
+///     Ok(ListToolsResult { tools: page.items, next_cursor: page.next_cursor })
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Paginator {
+    secret: Vec<u8>,
+    page_size: usize,
+}
+
+impl Paginator {
+    /// Create a paginator returning at most `page_size` items per page.
+    ///
+    /// Panics if `page_size` is zero.
+    pub fn new(secret: impl Into<Vec<u8>>, page_size: usize) -> Self {
+        assert!(page_size > 0, "page size must be positive");
+        Paginator {
+            secret: secret.into(),
+            page_size,
+        }
+    }
+
+    /// Return the page of `items` addressed by the cursor in `request`, or the first page.
+    ///
+    /// A cursor that was not issued by this paginator yields an `invalid_params` error.
+    pub fn paginate<T>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        request: Option<PaginatedRequestParam>,
+    ) -> Result<Page<T>, Error> {
+        let offset = match request.and_then(|r| r.cursor) {
+            Some(cursor) => self
+                .decode(&cursor)
+                .ok_or_else(|| Error::invalid_params("invalid cursor", None))?,
+            None => 0,
+        };
+
+        let mut items: Vec<T> = items
+            .into_iter()
+            .skip(offset)
+            .take(self.page_size + 1)
+            .collect();
+        let next_cursor = (items.len() > self.page_size).then(|| {
+            items.truncate(self.page_size);
+            self.encode(offset + self.page_size)
+        });
+        Ok(Page { items, next_cursor })
+    }
+
+    fn tag(&self, offset: u64) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(&offset.to_be_bytes());
+        mac
+    }
+
+    fn encode(&self, offset: usize) -> String {
+        let offset = offset as u64;
+        let tag = self.tag(offset).finalize().into_bytes();
+        offset
+            .to_be_bytes()
+            .iter()
+            .chain(&tag[..TAG_LEN])
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn decode(&self, cursor: &str) -> Option<usize> {
+        if cursor.len() != 2 * (8 + TAG_LEN) || !cursor.is_ascii() {
+            return None;
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let (offset, tag) = bytes.split_at(8);
+        let offset = u64::from_be_bytes(offset.try_into().ok()?);
+        self.tag(offset).verify_truncated_left(tag).ok()?;
+        usize::try_from(offset).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(cursor: Option<String>) -> Option<PaginatedRequestParam> {
+        Some(PaginatedRequestParam { cursor })
+    }
+
+    #[test]
+    fn test_paginate() {
+        let paginator = Paginator::new(b"secret".to_vec(), 2);
+
+        let first = paginator.paginate(1..=5, None).unwrap();
+        assert_eq!(first.items, vec![1, 2]);
+        let second = paginator
+            .paginate(1..=5, request(first.next_cursor))
+            .unwrap();
+        assert_eq!(second.items, vec![3, 4]);
+        let last = paginator
+            .paginate(1..=5, request(second.next_cursor))
+            .unwrap();
+        assert_eq!(
+            last,
+            Page {
+                items: vec![5],
+                next_cursor: None
+            }
+        );
+
+        assert_eq!(
+            paginator.paginate(1..=4, request(None)).unwrap().items,
+            vec![1, 2]
+        );
+        let exact = paginator.paginate(1..=2, None).unwrap();
+        assert_eq!(exact.next_cursor, None);
+        assert_eq!(
+            paginator.paginate(Vec::<u8>::new(), None),
+            Ok(Page {
+                items: vec![],
+                next_cursor: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_tampered_cursor() {
+        let paginator = Paginator::new("secret", 2);
+        let cursor = paginator
+            .paginate(1..=5, None)
+            .unwrap()
+            .next_cursor
+            .unwrap();
+        assert_eq!(cursor.len(), 48);
+
+        // Point the cursor at offset 3 without fixing the tag.
+        let forged = format!("{:016x}{}", 3, &cursor[16..]);
+        let invalid = Err(Error::invalid_params("invalid cursor", None));
+        assert_eq!(paginator.paginate(1..=5, request(Some(forged))), invalid);
+        assert_eq!(
+            paginator.paginate(1..=5, request(Some("2".to_string()))),
+            invalid
+        );
+        assert_eq!(
+            Paginator::new("other", 2).paginate(1..=5, request(Some(cursor))),
+            invalid
+        );
+    }
+}
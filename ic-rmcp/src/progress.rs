@@ -0,0 +1,70 @@
+//! Progress notifications for long-running requests.
+//!
+//! Reports are recorded on the request [`Context`] and delivered as `notifications/progress`
+//! ahead of the response when the client accepts `text/event-stream`.
+use crate::handler::Context;
+use crate::model::{
+    JsonRpcNotification, JsonRpcVersion2_0, ProgressNotification, ProgressNotificationParam,
+};
+
+impl Context {
+    /// Report progress on the current request, e.g. after each inter-canister call of a
+    /// multi-step tool.
+    ///
+    /// `progress` should increase with every call, even if `total` is unknown. Reports are only
+    /// recorded when the client sent a [`Context::progress_token`]; otherwise this is a no-op.
+    pub fn report_progress(&self, progress: u32, total: Option<u32>, message: Option<String>) {
+        if let Some(progress_token) = &self.progress_token {
+            self.notifications.push(JsonRpcNotification {
+                jsonrpc: JsonRpcVersion2_0,
+                notification: ProgressNotification::new(ProgressNotificationParam {
+                    progress_token: progress_token.clone(),
+                    progress: progress.into(),
+                    total: total.map(Into::into),
+                    message,
+                }),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{NumberOrString, ProgressToken};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_report_progress() {
+        let context = Context::default();
+        context.report_progress(1, None, None);
+        assert_eq!(context.notifications.take(), Vec::<Value>::new());
+
+        let context = Context {
+            progress_token: Some(ProgressToken(NumberOrString::Number(7))),
+            ..Default::default()
+        };
+        context.report_progress(1, Some(2), Some("fetched balance".to_string()));
+        context.clone().report_progress(2, Some(2), None);
+        assert_eq!(
+            context.notifications.take(),
+            vec![
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": {
+                        "progressToken": 7,
+                        "progress": 1.0,
+                        "total": 2.0,
+                        "message": "fetched balance"
+                    }
+                }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": {"progressToken": 7, "progress": 2.0, "total": 2.0}
+                }),
+            ]
+        );
+    }
+}
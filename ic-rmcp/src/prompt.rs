@@ -0,0 +1,151 @@
+//! Prompt helpers.
+//!
+//! Use [`prompt_arguments_for_type`] to describe prompt arguments with a typed struct, the same
+//! way tool inputs are described with [`schema_for_type`].
+use crate::{model::PromptArgument, schema_for_type, Error};
+use rmcp::schemars::JsonSchema;
+use serde_json::Value;
+
+/// Build the argument list of a prompt from a Rust type, the way [`schema_for_type`] describes
+/// tool inputs.
+///
+/// Every top-level property of the type's JSON Schema becomes a [`PromptArgument`]. Field doc
+/// comments become argument descriptions and non-optional fields are marked as required.
+///
+/// Clients send prompt arguments as strings, so every field must deserialize from one: a
+/// `String`, a unit-variant enum, or an `Option` of either. Any other field type, such as a
+/// number or a nested struct, yields an `internal_error`, so this can be used with `?` inside
+/// [`Handler::list_prompts`](crate::Handler::list_prompts).
+pub fn prompt_arguments_for_type<T: JsonSchema>() -> Result<Vec<PromptArgument>, Error> {
+    let schema = schema_for_type::<T>();
+    let root = Value::Object(schema.clone());
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| {
+                    if !is_string(&root, property) {
+                        return Err(Error::internal_error(
+                            format!(
+                                "prompt argument `{name}` of `{}` must be a string",
+                                T::schema_name()
+                            ),
+                            None,
+                        ));
+                    }
+                    Ok(PromptArgument {
+                        name: name.clone(),
+                        title: None,
+                        description: property
+                            .get("description")
+                            .and_then(Value::as_str)
+                            .map(str::to_string),
+                        required: Some(required.iter().any(|r| r.as_str() == Some(name))),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or(Ok(vec![]))
+}
+
+/// Whether values of `schema` are strings, or null, following references into `root`.
+fn is_string(root: &Value, schema: &Value) -> bool {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .is_some_and(|schema| is_string(root, schema));
+    }
+    if let Some(variants) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        return variants.iter().all(|variant| is_string(root, variant));
+    }
+    if schema.get("const") == Some(&Value::Null) {
+        return true;
+    }
+    match schema.get("type") {
+        Some(Value::String(kind)) => kind == "string",
+        Some(Value::Array(kinds)) => kinds.iter().all(|kind| kind == "string" || kind == "null"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::JsonSchema;
+
+    #[test]
+    fn test_prompt_arguments_for_type() {
+        #[allow(dead_code)]
+        #[derive(JsonSchema)]
+        struct Review {
+            /// The token symbol to review
+            symbol: String,
+            /// Optional focus area
+            focus: Option<String>,
+            depth: Depth,
+        }
+
+        #[allow(dead_code)]
+        #[derive(JsonSchema)]
+        enum Depth {
+            Quick,
+            Thorough,
+        }
+
+        assert_eq!(
+            prompt_arguments_for_type::<Review>(),
+            Ok(vec![
+                PromptArgument {
+                    name: "depth".to_string(),
+                    title: None,
+                    description: None,
+                    required: Some(true),
+                },
+                PromptArgument {
+                    name: "focus".to_string(),
+                    title: None,
+                    description: Some("Optional focus area".to_string()),
+                    required: Some(false),
+                },
+                PromptArgument {
+                    name: "symbol".to_string(),
+                    title: None,
+                    description: Some("The token symbol to review".to_string()),
+                    required: Some(true),
+                },
+            ])
+        );
+
+        assert_eq!(prompt_arguments_for_type::<String>(), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_prompt_arguments_for_type_rejects_numbers() {
+        #[allow(dead_code)]
+        #[derive(JsonSchema)]
+        struct Top {
+            limit: u32,
+        }
+
+        assert_eq!(
+            prompt_arguments_for_type::<Top>(),
+            Err(Error::internal_error(
+                "prompt argument `limit` of `Top` must be a string",
+                None
+            ))
+        );
+    }
+}
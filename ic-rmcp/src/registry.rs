@@ -0,0 +1,372 @@
+//! A tool registry populated at runtime.
+//!
+//! Register tools from your canister's `init`/`post_upgrade` hooks and serve the registry
+//! directly, or delegate to it from your own [`Handler`]:
+//!
+//! ```ignore
+//! thread_local! {
+//!     // Leaked so that `handle` can borrow it for the lifetime of the response.
+//!     static TOOLS: &'static ToolRegistry = Box::leak(Box::new(ToolRegistry::new()));
+//! }
+//!
+//! #[init]
+//! fn init() {
+//!     TOOLS.with(|tools| {
+//!         tools.register_typed::<AddRequest, _, _>("add", "Add two numbers", |_, args| async move {
+//!             Ok(CallToolResult::success(Content::text(format!("{}", args.a + args.b)).into_contents()))
+//!         })
+//!     });
+//! }
+//!
+//! #[update(guard = "is_controller")]
+//! fn disable_tool(name: String) -> bool {
+//!     TOOLS.with(|tools| tools.disable(&name))
+//! }
+//! ```
+//!
+//! The enabled state lives on the heap; persist it yourself if it must survive upgrades.
+//! Registering, unregistering, enabling and disabling tools invalidates certified `tools/list`
+//! responses (see [`init_certification`](crate::init_certification)), so do it from update calls.
+use crate::{
+    handler::{Context, Handler},
+    invalidate_certified_responses,
+    model::*,
+    parse_arguments, schema_for_type, tool_not_found, Error, Paginator, ServerConfig,
+};
+use rmcp::schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+type ToolFuture = Pin<Box<dyn Future<Output = Result<CallToolResult, Error>>>>;
+type ToolFn = Rc<dyn Fn(Context, Option<JsonObject>) -> ToolFuture>;
+
+struct Entry {
+    tool: Tool,
+    enabled: bool,
+    handler: ToolFn,
+}
+
+/// Tools registered at runtime, each with its own async handler.
+///
+/// Clones share the same registry. Tools are listed in registration order; registering a name
+/// again replaces the previous entry.
+#[derive(Clone)]
+pub struct ToolRegistry {
+    info: ServerInfo,
+    config: ServerConfig,
+    paginator: Option<Paginator>,
+    entries: Rc<RefCell<Vec<Entry>>>,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        ToolRegistry {
+            info: ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            },
+            config: ServerConfig::default(),
+            paginator: None,
+            entries: Rc::default(),
+        }
+    }
+}
+
+impl ToolRegistry {
+    /// Create an empty registry advertising the `tools` capability.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the server info returned on `initialize`.
+    pub fn with_info(mut self, info: ServerInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Set the paths the registry is served at.
+    pub fn with_config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Split `tools/list` results into pages; by default all enabled tools are listed at once.
+    pub fn with_paginator(mut self, paginator: Paginator) -> Self {
+        self.paginator = Some(paginator);
+        self
+    }
+
+    /// Register a tool taking raw JSON arguments.
+    pub fn register<F, Fut>(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: JsonObject,
+        handler: F,
+    ) where
+        F: Fn(Context, Option<JsonObject>) -> Fut + 'static,
+        Fut: Future<Output = Result<CallToolResult, Error>> + 'static,
+    {
+        let tool = Tool::new(name.into(), description.into(), input_schema);
+        let handler: ToolFn =
+            Rc::new(move |context, arguments| Box::pin(handler(context, arguments)));
+
+        let mut entries = self.entries.borrow_mut();
+        let entry = Entry {
+            tool,
+            enabled: true,
+            handler,
+        };
+        match entries.iter_mut().find(|e| e.tool.name == entry.tool.name) {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+        invalidate_certified_responses();
+    }
+
+    /// Register a tool whose arguments deserialize into `T`, using `T`'s JSON Schema as input
+    /// schema. Invalid arguments are rejected with `invalid_params` before `handler` runs.
+    pub fn register_typed<T, F, Fut>(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) where
+        T: DeserializeOwned + JsonSchema + 'static,
+        F: Fn(Context, T) -> Fut + 'static,
+        Fut: Future<Output = Result<CallToolResult, Error>> + 'static,
+    {
+        let name = name.into();
+        let tool = name.clone();
+        let handler = Rc::new(handler);
+        self.register(
+            name,
+            description,
+            schema_for_type::<T>(),
+            move |context, arguments| {
+                let handler = handler.clone();
+                let arguments = parse_arguments::<T>(&tool, arguments);
+                async move { handler(context, arguments?).await }
+            },
+        );
+    }
+
+    /// Remove a tool. Returns whether it was registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut entries = self.entries.borrow_mut();
+        let len = entries.len();
+        entries.retain(|entry| entry.tool.name != name);
+        invalidate_certified_responses();
+        entries.len() != len
+    }
+
+    /// Make a tool visible and callable again. Returns whether it is registered.
+    pub fn enable(&self, name: &str) -> bool {
+        self.set_enabled(name, true)
+    }
+
+    /// Hide a tool from `tools/list` and reject calls to it. Returns whether it is registered.
+    pub fn disable(&self, name: &str) -> bool {
+        self.set_enabled(name, false)
+    }
+
+    fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        invalidate_certified_responses();
+        self.entries
+            .borrow_mut()
+            .iter_mut()
+            .find(|entry| entry.tool.name == name)
+            .map(|entry| entry.enabled = enabled)
+            .is_some()
+    }
+
+    /// Whether a tool is registered and enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.entries
+            .borrow()
+            .iter()
+            .any(|entry| entry.tool.name == name && entry.enabled)
+    }
+
+    /// The enabled tools, in registration order.
+    pub fn tools(&self) -> Vec<Tool> {
+        self.entries
+            .borrow()
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.tool.clone())
+            .collect()
+    }
+
+    /// Run the handler of an enabled tool.
+    pub async fn call(
+        &self,
+        context: Context,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, Error> {
+        // Release the borrow before awaiting so handlers may update the registry.
+        let handler = self
+            .entries
+            .borrow()
+            .iter()
+            .find(|entry| entry.tool.name == request.name && entry.enabled)
+            .map(|entry| entry.handler.clone());
+
+        match handler {
+            Some(handler) => handler(context, request.arguments).await,
+            None => Err(tool_not_found(&request.name)),
+        }
+    }
+}
+
+impl Handler for ToolRegistry {
+    fn get_info(&self, _: Context) -> ServerInfo {
+        self.info.clone()
+    }
+
+    fn server_config(&self) -> ServerConfig {
+        self.config.clone()
+    }
+
+    async fn list_tools(
+        &self,
+        _: Context,
+        request: Option<PaginatedRequestParam>,
+    ) -> Result<ListToolsResult, Error> {
+        match &self.paginator {
+            Some(paginator) => {
+                let page = paginator.paginate(self.tools(), request)?;
+                Ok(ListToolsResult {
+                    next_cursor: page.next_cursor,
+                    tools: page.items,
+                })
+            }
+            None => Ok(ListToolsResult {
+                next_cursor: None,
+                tools: self.tools(),
+            }),
+        }
+    }
+
+    async fn call_tool(
+        &self,
+        context: Context,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, Error> {
+        self.call(context, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize, JsonSchema)]
+    struct Echo {
+        text: String,
+    }
+
+    fn registry() -> ToolRegistry {
+        let registry = ToolRegistry::new();
+        registry.register_typed::<Echo, _, _>("echo", "Echo text", |_, args| async move {
+            Ok(CallToolResult::success(
+                Content::text(args.text).into_contents(),
+            ))
+        });
+        registry.register(
+            "ping",
+            "Reply pong",
+            schema_for_type::<EmptyObject>(),
+            |_, _| async {
+                Ok(CallToolResult::success(
+                    Content::text("pong").into_contents(),
+                ))
+            },
+        );
+        registry
+    }
+
+    fn call(
+        registry: &ToolRegistry,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<CallToolResult, Error> {
+        block_on(registry.call_tool(
+            Context::default(),
+            CallToolRequestParam {
+                name: name.to_string().into(),
+                arguments: arguments.as_object().cloned(),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = registry();
+        assert_eq!(
+            registry.get_info(Context::default()).capabilities,
+            ServerCapabilities::builder().enable_tools().build()
+        );
+        assert_eq!(
+            registry.tools(),
+            vec![
+                Tool::new("echo", "Echo text", schema_for_type::<Echo>()),
+                Tool::new("ping", "Reply pong", schema_for_type::<EmptyObject>()),
+            ]
+        );
+
+        assert_eq!(
+            call(&registry, "echo", json!({"text": "hi"})),
+            Ok(CallToolResult::success(Content::text("hi").into_contents()))
+        );
+        assert_eq!(
+            call(&registry, "echo", json!({})),
+            Err(Error::invalid_params(
+                "invalid arguments to tool echo: missing field `text`",
+                None
+            ))
+        );
+
+        assert!(registry.clone().disable("echo"));
+        assert!(!registry.is_enabled("echo"));
+        assert_eq!(
+            block_on(registry.list_tools(Context::default(), None)).map(|r| r.tools.len()),
+            Ok(1)
+        );
+        assert_eq!(
+            call(&registry, "echo", json!({"text": "hi"})),
+            Err(Error::invalid_params("not found tool echo", None))
+        );
+
+        assert!(registry.enable("echo"));
+        assert!(registry.is_enabled("echo"));
+        assert!(!registry.enable("unknown"));
+
+        registry.register("echo", "Replaced", JsonObject::new(), |_, _| async {
+            Ok(CallToolResult::success(vec![]))
+        });
+        assert_eq!(registry.tools()[0].description.as_deref(), Some("Replaced"));
+
+        let paged = registry.clone().with_paginator(Paginator::new("secret", 1));
+        let page = block_on(paged.list_tools(Context::default(), None)).unwrap();
+        assert_eq!(page.tools.len(), 1);
+        let page = block_on(paged.list_tools(
+            Context::default(),
+            Some(PaginatedRequestParam {
+                cursor: page.next_cursor,
+            }),
+        ))
+        .unwrap();
+        assert_eq!((page.tools.len(), page.next_cursor), (1, None));
+
+        assert!(registry.unregister("echo"));
+        assert!(!registry.unregister("echo"));
+        assert_eq!(registry.tools().len(), 1);
+    }
+}
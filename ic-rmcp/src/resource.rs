@@ -0,0 +1,332 @@
+//! Resource template helpers.
+//!
+//! This module provides:
+//! - [`UriTemplate`] to parse RFC 6570 URI templates such as `canister://ledger/accounts/{principal}`
+//! - [`UriParams`] holding the variables extracted from a matching `resources/read` URI
+//!
+//! Supported expressions are simple string expansion (`{var}`), which matches a single
+//! non-empty path segment, and reserved expansion (`{+var}`), which may also span `/`. A template
+//! may contain at most one reserved expression, which keeps matching linear in practice.
+use crate::{model::ResourceTemplate, Error};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// A parsed URI template used to match incoming resource URIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UriTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Variable { name: String, reserved: bool },
+}
+
+impl UriTemplate {
+    /// Parse a URI template.
+    ///
+    /// Errors are returned as strings describing the malformed expression.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or(format!("unclosed expression in template `{template}`"))?;
+
+            let (name, reserved) = match rest[start + 1..end].strip_prefix('+') {
+                Some(name) => (name, true),
+                None => (&rest[start + 1..end], false),
+            };
+
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+            {
+                return Err(format!(
+                    "unsupported expression `{}` in template `{template}`",
+                    &rest[start..=end]
+                ));
+            }
+
+            if let Some(Part::Variable { .. }) = parts.last() {
+                return Err(format!(
+                    "adjacent expressions are ambiguous in template `{template}`"
+                ));
+            }
+
+            if reserved
+                && parts
+                    .iter()
+                    .any(|part| matches!(part, Part::Variable { reserved: true, .. }))
+            {
+                return Err(format!(
+                    "more than one reserved expression in template `{template}`"
+                ));
+            }
+
+            parts.push(Part::Variable {
+                name: name.to_string(),
+                reserved,
+            });
+            rest = &rest[end + 1..];
+        }
+
+        if rest.contains('}') {
+            return Err(format!("unexpected `}}` in template `{template}`"));
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        Ok(UriTemplate {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    /// The original template string.
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Match a concrete URI against this template, returning the extracted variables.
+    ///
+    /// Values are percent-decoded. Returns `None` when the URI does not match.
+    pub fn matches(&self, uri: &str) -> Option<UriParams> {
+        let mut params = BTreeMap::new();
+        match_parts(&self.parts, uri, &mut params).then_some(UriParams(params))
+    }
+}
+
+fn match_parts(parts: &[Part], input: &str, params: &mut BTreeMap<String, String>) -> bool {
+    match parts.split_first() {
+        None => input.is_empty(),
+        Some((Part::Literal(literal), rest)) => input
+            .strip_prefix(literal.as_str())
+            .is_some_and(|input| match_parts(rest, input, params)),
+        Some((Part::Variable { name, reserved }, rest)) => {
+            let limit = match reserved {
+                true => input.len(),
+                false => input.find(['/', '?', '#']).unwrap_or(input.len()),
+            };
+
+            // Variables are always followed by a literal or the end of the URI, so only the
+            // positions where that literal starts are candidates. Prefer the longest value so
+            // that trailing literals bind as late as possible, and decode it once the rest of
+            // the URI matched.
+            let next = match rest.first() {
+                Some(Part::Literal(literal)) => Some(literal.as_str()),
+                _ => None,
+            };
+            for end in (1..=limit).rev().filter(|end| input.is_char_boundary(*end)) {
+                let candidate = match next {
+                    Some(literal) => input[end..].starts_with(literal),
+                    None => end == input.len(),
+                };
+                if candidate && match_parts(rest, &input[end..], params) {
+                    if let Some(value) = percent_decode(&input[..end]) {
+                        params.insert(name.clone(), value);
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+    }
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value.get(i + 1..i + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Variables extracted from a resource URI by a matching [`UriTemplate`].
+///
+/// Populated on [`Context::uri_params`](crate::Context::uri_params) when a `resources/read`
+/// request matches one of the templates returned by
+/// [`Handler::list_resource_templates`](crate::Handler::list_resource_templates).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UriParams(BTreeMap<String, String>);
+
+impl UriParams {
+    /// Get the raw (percent-decoded) value of a variable.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(|value| value.as_str())
+    }
+
+    /// Parse a variable into a typed value.
+    ///
+    /// Missing or unparsable variables yield an `invalid_params` error, so this can be used
+    /// with `?` directly inside [`Handler::read_resource`](crate::Handler::read_resource).
+    pub fn parse<T: FromStr>(&self, name: &str) -> Result<T, Error> {
+        self.get(name)
+            .and_then(|value| value.parse::<T>().ok())
+            .ok_or(Error::invalid_params(
+                format!("invalid uri parameter `{name}`"),
+                None,
+            ))
+    }
+
+    /// Iterate over all extracted variables.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Find the first template matching `uri`, returning its URI template and extracted variables.
+///
+/// Templates that fail to parse are skipped.
+pub fn match_resource_template(
+    templates: &[ResourceTemplate],
+    uri: &str,
+) -> Option<(String, UriParams)> {
+    templates.iter().find_map(|template| {
+        UriTemplate::parse(&template.uri_template)
+            .ok()
+            .and_then(|parsed| parsed.matches(uri))
+            .map(|params| (template.uri_template.clone(), params))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AnnotateAble, RawResourceTemplate};
+
+    #[test]
+    fn test_parse() {
+        assert!(UriTemplate::parse("canister://ledger/accounts/{principal}").is_ok());
+        assert!(UriTemplate::parse("file:///{+path}").is_ok());
+        assert!(UriTemplate::parse("canister://static").is_ok());
+
+        assert!(UriTemplate::parse("canister://{a")
+            .unwrap_err()
+            .contains("unclosed expression"));
+        assert!(UriTemplate::parse("canister://{}")
+            .unwrap_err()
+            .contains("unsupported expression"));
+        assert!(UriTemplate::parse("canister://{?a,b}")
+            .unwrap_err()
+            .contains("unsupported expression"));
+        assert!(UriTemplate::parse("canister://{a}{b}")
+            .unwrap_err()
+            .contains("adjacent expressions"));
+        assert!(UriTemplate::parse("file:///{+a}-{+b}-{+c}")
+            .unwrap_err()
+            .contains("more than one reserved expression"));
+        assert!(UriTemplate::parse("file:///{+a}/{b}/{c}").is_ok());
+        assert!(UriTemplate::parse("canister://a}")
+            .unwrap_err()
+            .contains("unexpected `}`"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let template = UriTemplate::parse("canister://ledger/accounts/{principal}").unwrap();
+        assert_eq!(template.as_str(), "canister://ledger/accounts/{principal}");
+
+        let params = template
+            .matches("canister://ledger/accounts/aaaaa-aa")
+            .unwrap();
+        assert_eq!(params.get("principal"), Some("aaaaa-aa"));
+        assert_eq!(params.get("foo"), None);
+
+        assert_eq!(template.matches("canister://ledger/accounts/"), None);
+        assert_eq!(template.matches("canister://ledger/accounts/a/b"), None);
+        assert_eq!(template.matches("canister://ledger/blocks/1"), None);
+
+        let template = UriTemplate::parse("canister://blocks/{from}-{to}.json").unwrap();
+        let params = template.matches("canister://blocks/10-20.json").unwrap();
+        assert_eq!(params.parse::<u64>("from"), Ok(10));
+        assert_eq!(params.parse::<u64>("to"), Ok(20));
+        assert_eq!(
+            params.parse::<u64>("foo"),
+            Err(Error::invalid_params("invalid uri parameter `foo`", None))
+        );
+
+        let template = UriTemplate::parse("file:///{+path}").unwrap();
+        let params = template.matches("file:///docs/read%20me.md").unwrap();
+        assert_eq!(params.get("path"), Some("docs/read me.md"));
+        assert_eq!(template.matches("file:///docs/%zz"), None);
+
+        // Long URIs are matched without decoding every prefix.
+        let long = format!("file:///{}", "a/".repeat(50_000));
+        assert_eq!(
+            template.matches(&long).unwrap().get("path").map(str::len),
+            Some(100_000)
+        );
+        let template = UriTemplate::parse("file:///{+path}.md").unwrap();
+        assert_eq!(template.matches(&long), None);
+
+        // A reserved expression followed by simple ones only backtracks over its own value.
+        let template = UriTemplate::parse("file:///{+dir}/{name}-{version}.tar").unwrap();
+        let params = template.matches("file:///a/b/pkg-1.2.tar").unwrap();
+        assert_eq!(params.get("dir"), Some("a/b"));
+        assert_eq!(params.get("name"), Some("pkg"));
+        assert_eq!(params.get("version"), Some("1.2"));
+        let long = format!("file:///{}", "a-".repeat(50_000));
+        assert_eq!(template.matches(&long), None);
+    }
+
+    #[test]
+    fn test_match_resource_template() {
+        let templates = vec![
+            RawResourceTemplate {
+                uri_template: "canister://{broken".to_string(),
+                name: "broken".to_string(),
+                title: None,
+                description: None,
+                mime_type: None,
+            }
+            .no_annotation(),
+            RawResourceTemplate {
+                uri_template: "canister://ledger/accounts/{principal}".to_string(),
+                name: "account".to_string(),
+                title: None,
+                description: None,
+                mime_type: None,
+            }
+            .no_annotation(),
+        ];
+
+        let (template, params) =
+            match_resource_template(&templates, "canister://ledger/accounts/aaaaa-aa").unwrap();
+        assert_eq!(template, "canister://ledger/accounts/{principal}");
+        assert_eq!(
+            params.iter().collect::<Vec<_>>(),
+            vec![("principal", "aaaaa-aa")]
+        );
+
+        assert_eq!(
+            match_resource_template(&templates, "canister://ledger/blocks/1"),
+            None
+        );
+    }
+}
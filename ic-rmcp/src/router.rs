@@ -0,0 +1,91 @@
+//! Tool routing support for the `#[tool_router]` and `#[tool_handler]` macros.
+//!
+//! ```ignore
+//! #[tool_router]
+//! impl Adder {
+//!     /// Add two numbers
+//!     #[tool]
+//!     async fn add(&self, args: AddRequest) -> Result<CallToolResult, Error> {
+//!         Ok(CallToolResult::success(Content::text(format!("{}", args.a + args.b)).into_contents()))
+//!     }
+//! }
+//!
+//! #[tool_handler]
+//! impl Handler for Adder {}
+//! ```
+use crate::{
+    handler::Context,
+    model::{CallToolRequestParam, CallToolResult, JsonObject, Tool},
+    Error,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{from_value, Value};
+use std::future::Future;
+
+/// A set of tools with typed arguments, usually generated by `#[tool_router]`.
+pub trait ToolRouter {
+    /// Tools advertised by `tools/list`.
+    fn tools() -> Vec<Tool>;
+    /// Dispatch a `tools/call` request to the matching tool.
+    fn route(
+        &self,
+        context: Context,
+        request: CallToolRequestParam,
+    ) -> impl Future<Output = Result<CallToolResult, Error>>;
+    /// Whether the tool named `name` was marked `#[tool(query)]`; see
+    /// [`Handler::query_tool`](crate::Handler::query_tool).
+    fn is_query_tool(name: &str) -> bool {
+        let _ = name;
+        false
+    }
+}
+
+/// Deserialize the arguments of a `tools/call` request into `T`.
+///
+/// Missing arguments are treated as an empty object. Failures yield an `invalid_params` error
+/// naming the tool.
+pub fn parse_arguments<T: DeserializeOwned>(
+    tool: &str,
+    arguments: Option<JsonObject>,
+) -> Result<T, Error> {
+    from_value(Value::Object(arguments.unwrap_or_default())).map_err(|err| {
+        Error::invalid_params(format!("invalid arguments to tool {tool}: {err}"), None)
+    })
+}
+
+/// The error returned for a `tools/call` naming an unknown tool.
+pub fn tool_not_found(tool: &str) -> Error {
+    Error::invalid_params(format!("not found tool {tool}"), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_arguments() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Args {
+            a: u8,
+            b: Option<u8>,
+        }
+
+        assert_eq!(
+            parse_arguments::<Args>("add", json!({"a": 1}).as_object().cloned()),
+            Ok(Args { a: 1, b: None })
+        );
+        assert_eq!(
+            parse_arguments::<Args>("add", None),
+            Err(Error::invalid_params(
+                "invalid arguments to tool add: missing field `a`",
+                None
+            ))
+        );
+        assert_eq!(
+            tool_not_found("sub"),
+            Error::invalid_params("not found tool sub", None)
+        );
+    }
+}
@@ -1,18 +1,243 @@
 use crate::handler::oauth::OAuthConfig;
-use ic_http_certification::{HeaderField, HttpRequest, HttpResponse};
+use ic_http_certification::{HeaderField, HttpRequest, HttpResponse, StatusCode};
 use std::future::Future;
+use std::time::Duration;
+
+/// Where a server is mounted, set by [`Handler::server_config`](crate::Handler::server_config).
+///
+/// ```ignore
+/// fn server_config(&self) -> ServerConfig {
+///     ServerConfig {
+///         paths: vec!["/mcp".to_string(), "/api/v1/mcp".to_string()],
+///         fallthrough: Some(routes),
+///         cors: Some(CorsPolicy::default()),
+///         ..Default::default()
+///     }
+/// }
+///
+/// fn routes(req: &HttpRequest) -> HttpResponse<'static> {
+///     let status = match req.get_path().as_deref() {
+///         Ok("/health") => StatusCode::OK,
+///         _ => StatusCode::NOT_FOUND,
+///     };
+///     HttpResponse::builder().with_status_code(status).build()
+/// }
+/// ```
+///
+/// To serve several MCP servers from one canister, give each handler its own paths and pick the
+/// handler whose [`ServerConfig::matches`] the request.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Paths the server answers on, such as `/mcp` or `/api/v1/mcp`. Matched exactly, ignoring
+    /// the query string.
+    pub paths: Vec<String>,
+    /// Answers requests to other paths instead of the `404` response, for example with the
+    /// canister's own routes. Called from both the query and the update endpoint; the query
+    /// only serves responses carrying their own `IC-Certificate` header and upgrades the rest.
+    pub fallthrough: Option<fn(&HttpRequest) -> HttpResponse<'static>>,
+    /// Lets browser clients call the server from other origins. Without it, no CORS headers
+    /// are sent and `OPTIONS` requests are not answered.
+    pub cors: Option<CorsPolicy>,
+    /// Rejects requests from origins that are not allowed, as the MCP transport specification
+    /// requires to prevent DNS rebinding attacks. Without it, the `Origin` header is ignored.
+    ///
+    /// With both policies set, an origin must be allowed by this one to get a response at all,
+    /// and by [`ServerConfig::cors`] to get CORS headers; the default `CorsPolicy`, allowing
+    /// `*`, thus grants exactly the origins allowed here.
+    pub origins: Option<OriginPolicy>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            paths: vec!["/mcp".to_string()],
+            fallthrough: None,
+            cors: None,
+            origins: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Whether `req` is addressed to one of [`ServerConfig::paths`].
+    pub fn matches(&self, req: &HttpRequest) -> bool {
+        req.get_path().is_ok_and(|path| self.paths.contains(&path))
+    }
+}
+
+/// Which `Origin`s may send requests to the server.
+///
+/// Requests from other origins get a `403` before authorization, from the update call: queries
+/// upgrade them. Fallthrough routes are not checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginPolicy {
+    /// Allowed origins, such as `https://app.example.com`.
+    pub allowed_origins: Vec<String>,
+    /// Accept requests without an `Origin` header, as sent by non-browser clients.
+    pub allow_missing: bool,
+}
+
+impl Default for OriginPolicy {
+    fn default() -> Self {
+        OriginPolicy {
+            allowed_origins: vec![],
+            allow_missing: true,
+        }
+    }
+}
+
+impl OriginPolicy {
+    /// Whether a request with the given `Origin` header, if any, is allowed.
+    pub fn allows(&self, origin: Option<&str>) -> bool {
+        match origin {
+            Some(origin) => self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin)),
+            None => self.allow_missing,
+        }
+    }
+
+    /// A `403` response if the request's origin is not allowed.
+    pub(crate) fn check(&self, req: &HttpRequest) -> Option<HttpResponse<'static>> {
+        let origin = req
+            .headers()
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Origin"))
+            .map(|(_, origin)| origin.as_str());
+        match self.allows(origin) {
+            true => None,
+            false => Some(
+                HttpResponse::builder()
+                    .with_status_code(StatusCode::FORBIDDEN)
+                    .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+                    .with_body(br#"Origin not allowed"#)
+                    .build(),
+            ),
+        }
+    }
+}
+
+/// Cross-origin access for browser clients, such as a web-based MCP inspector.
+///
+/// `OPTIONS` preflights are answered without authorization, from the query endpoint as well
+/// once an update call has certified the response to the same preflight (given
+/// [`init_certification`](crate::init_certification)), and every response to an allowed
+/// origin carries `Access-Control-Allow-Origin` and exposes the `Mcp-Session-Id` and
+/// `WWW-Authenticate` headers. An [`OriginPolicy`] in the same
+/// [`ServerConfig`] narrows the allowed origins further: requests and preflights from origins
+/// it rejects get a `403` without CORS headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsPolicy {
+    /// Origins allowed to call the server, such as `https://inspector.example.com`. `*` allows
+    /// any origin.
+    pub allowed_origins: Vec<String>,
+    /// Request headers browsers may send.
+    pub allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight response.
+    pub max_age: Duration,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        CorsPolicy {
+            allowed_origins: vec!["*".to_string()],
+            allowed_headers: [
+                "Accept",
+                "Authorization",
+                "Content-Type",
+                "Mcp-Session-Id",
+                "MCP-Protocol-Version",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+            max_age: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+impl CorsPolicy {
+    /// Whether requests from `origin` are allowed.
+    pub fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(origin))
+    }
+
+    /// The request's `Origin`, if it is allowed.
+    fn allowed_origin(&self, req: &HttpRequest) -> Option<String> {
+        req.headers()
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Origin"))
+            .map(|(_, origin)| origin.clone())
+            .filter(|origin| self.allows(origin))
+    }
+
+    /// The response to a preflight request. Disallowed origins get no CORS headers, which makes
+    /// the browser refuse the actual request.
+    pub(crate) fn preflight(&self, req: &HttpRequest) -> HttpResponse<'static> {
+        let mut headers = vec![];
+        if let Some(origin) = self.allowed_origin(req) {
+            headers = vec![
+                ("Access-Control-Allow-Origin".to_string(), origin),
+                (
+                    "Access-Control-Allow-Methods".to_string(),
+                    "GET, POST, DELETE, OPTIONS".to_string(),
+                ),
+                (
+                    "Access-Control-Allow-Headers".to_string(),
+                    self.allowed_headers.join(", "),
+                ),
+                (
+                    "Access-Control-Max-Age".to_string(),
+                    self.max_age.as_secs().to_string(),
+                ),
+                ("Vary".to_string(), "Origin".to_string()),
+            ];
+        }
+        HttpResponse::builder()
+            .with_status_code(StatusCode::NO_CONTENT)
+            .with_headers(headers)
+            .build()
+    }
+
+    /// Add the CORS headers for the request's origin to `res`.
+    pub(crate) fn decorate<'a>(
+        &self,
+        req: &HttpRequest,
+        mut res: HttpResponse<'a>,
+    ) -> HttpResponse<'a> {
+        if let Some(origin) = self.allowed_origin(req) {
+            res.add_header(("Access-Control-Allow-Origin".to_string(), origin));
+            res.add_header((
+                "Access-Control-Expose-Headers".to_string(),
+                "Mcp-Session-Id, WWW-Authenticate".to_string(),
+            ));
+            res.add_header(("Vary".to_string(), "Origin".to_string()));
+        }
+        res
+    }
+}
 
 /// Entry points for serving MCP over the IC Streamable HTTP interface.
 ///
 /// This trait is blanket-implemented for all [`Handler`](crate::Handler) implementors.
 /// Use one of the methods below from inside your canister's `http_request`/`http_request_update`
-/// to process MCP JSON-RPC messages posted to `/mcp`.
+/// to process MCP JSON-RPC messages posted to the paths of the handler's [`ServerConfig`]
+/// (`/mcp` by default).
+///
+/// Replies are `application/json`. When the client's `Accept` header lists `text/event-stream`
+/// and the handler emitted notifications (for example via [`Context::log`](crate::Context::log)),
+/// the reply is instead an SSE stream of those notifications followed by the JSON-RPC response.
 pub trait Server {
     /// Handle a request using a caller-provided authorization predicate.
     ///
     /// - If `auth(headers)` returns `false`, a `401 Unauthorized` response is returned.
-    /// - Otherwise, the request is processed. Only `POST` requests to paths ending with `/mcp`
-    ///   are accepted; other methods or paths yield a `404` with a helpful message.
+    /// - Otherwise, the request is processed. Only `POST` requests to the configured paths are
+    ///   accepted; other methods or paths yield a `404` with a helpful message.
+    /// - Requests to other paths go to [`ServerConfig::fallthrough`] instead, before `auth` is
+    ///   checked, when it is set.
+    /// - Requests from origins rejected by [`ServerConfig::origins`] yield a `403`.
     ///
     /// Typical usage is API-key or custom header checks.
     fn handle(
@@ -20,9 +245,33 @@ pub trait Server {
         req: &HttpRequest,
         auth: impl Fn(&[HeaderField]) -> bool,
     ) -> impl Future<Output = HttpResponse<'_>>;
+    /// Like [`Server::handle`], for the `http_request` query endpoint.
+    ///
+    /// Query responses come from a single replica, so HTTP gateways only accept them with a
+    /// certificate. Without [`init_certification`](crate::init_certification), every MCP request
+    /// gets an upgrade response and the gateway repeats it as `http_request_update`. With it,
+    /// the query call answers the requests below, saving the cycles and latency of consensus,
+    /// once `init_certification` or an update call has certified the same response to an
+    /// identical request (same body and `Authorization`, `Mcp-Session-Id`,
+    /// `MCP-Protocol-Version` and `Origin` headers):
+    /// - `initialize` (unless sessions are enabled), `ping` and `tools/list`
+    /// - `tools/call` for tools opted in with [`Handler::query_tool`](crate::Handler::query_tool)
+    /// - CORS preflights
+    ///
+    /// Everything else is upgraded, including batches, `401` and `403` rejections and preflights
+    /// not certified yet, so that query and update calls behave alike. Fallthrough responses are
+    /// served only if they carry their own certificate. Sessions touched by a query call do not
+    /// have their idle timeout extended, since the state changes of a query are discarded.
+    fn handle_query(
+        &self,
+        req: &HttpRequest,
+        auth: impl Fn(&[HeaderField]) -> bool,
+    ) -> impl Future<Output = HttpResponse<'_>>;
     /// Handle a request with OAuth protection and metadata support.
     ///
     /// Behavior:
+    /// - Rejects requests from origins not allowed by [`ServerConfig::origins`] with `403`,
+    ///   including requests for the metadata document.
     /// - Serves resource metadata when the client performs `GET` on the metadata URL path.
     /// - Requires a `Bearer` token on protected endpoints; missing/invalid tokens result in `401`
     ///   with a `WWW-Authenticate` challenge referencing the provided metadata URL.
@@ -32,4 +281,15 @@ pub trait Server {
         req: &HttpRequest,
         cfg: OAuthConfig,
     ) -> impl Future<Output = HttpResponse<'_>>;
+    /// Like [`Server::handle_with_oauth`], for the `http_request` query endpoint.
+    ///
+    /// Serves what [`Server::handle_query`] serves, plus the metadata document once certified by
+    /// an update call. Requests are
+    /// upgraded until the issuer's keys have been fetched by an update call, since queries
+    /// cannot make HTTPS outcalls.
+    fn handle_query_with_oauth(
+        &self,
+        req: &HttpRequest,
+        cfg: OAuthConfig,
+    ) -> impl Future<Output = HttpResponse<'_>>;
 }
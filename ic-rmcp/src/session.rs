@@ -0,0 +1,605 @@
+//! Optional `Mcp-Session-Id` sessions persisted in stable memory.
+//!
+//! Call [`init_sessions`] from your canister's `init` and `post_upgrade` hooks to enable them.
+//! Once enabled:
+//! - a successful `initialize` response carries a new `Mcp-Session-Id` header
+//! - every later request must echo it; a missing id yields `400`, an unknown or expired one `404`
+//! - `DELETE /mcp` with the header terminates the session
+//!
+//! With [`SessionConfig::strict`], the MCP lifecycle is enforced as well: requests other than
+//! `ping` fail until the client sends `notifications/initialized`, and every request's
+//! `MCP-Protocol-Version` header must match the version negotiated on `initialize`.
+//!
+//! Session ids are drawn from the management canister's `raw_rand`, so `initialize` must be
+//! served from `http_request_update`. Without [`init_sessions`] the server stays stateless.
+use crate::handler::Context;
+use crate::model::{
+    ClientCapabilities, Implementation, InitializeRequestParam, LoggingLevel, ProtocolVersion,
+};
+use crate::state::SESSIONS;
+use ic_http_certification::{HttpRequest, HttpResponse, StatusCode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_slice, to_value, to_vec, Value};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Settings for [`init_sessions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// How long a session stays valid after its last request.
+    pub ttl: Duration,
+    /// Enforce the `initialize`/`initialized` lifecycle and protocol version header.
+    pub strict: bool,
+    /// Most sessions kept at once; `initialize` fails while the store is full.
+    pub max_sessions: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            ttl: Duration::from_secs(60 * 60),
+            strict: false,
+            max_sessions: 10_000,
+        }
+    }
+}
+
+/// Enable sessions, storing them in `memory`.
+///
+/// `memory` should be a dedicated virtual memory from your canister's `MemoryManager`; sessions
+/// created before an upgrade remain valid after it as long as the same memory is passed again.
+pub fn init_sessions(memory: VirtualMemory<DefaultMemoryImpl>, config: SessionConfig) {
+    SESSIONS.with_borrow_mut(|sessions| *sessions = Some(SessionStore::new(memory, config)));
+}
+
+/// What the server remembers about a client between requests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Session {
+    pub protocol_version: ProtocolVersion,
+    pub client_info: Implementation,
+    pub client_capabilities: ClientCapabilities,
+    pub expires_at: u64,
+    /// Whether the client sent `notifications/initialized`.
+    #[serde(default)]
+    pub initialized: bool,
+    /// Level set with `logging/setLevel`, if any.
+    #[serde(default)]
+    pub log_level: Option<LoggingLevel>,
+}
+
+impl Storable for Session {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(to_vec(self).expect("serialize session"))
+    }
+
+    /// Sessions that cannot be decoded, e.g. written by an incompatible version, are treated as
+    /// expired.
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        from_slice(&bytes).unwrap_or_else(|err| {
+            ic_cdk::eprintln!("Deserialize session: {}", err);
+            Session {
+                protocol_version: ProtocolVersion::default(),
+                client_info: Implementation::default(),
+                client_capabilities: ClientCapabilities::default(),
+                expires_at: 0,
+                initialized: false,
+                log_level: None,
+            }
+        })
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Most sessions checked for expiry on each `initialize`.
+const SWEEP: usize = 16;
+
+pub(crate) struct SessionStore {
+    sessions: StableBTreeMap<String, Session, VirtualMemory<DefaultMemoryImpl>>,
+    ttl: u64,
+    strict: bool,
+    max_sessions: u64,
+    /// Id at which the next expiry sweep starts.
+    sweep_from: Option<String>,
+}
+
+impl SessionStore {
+    fn new(memory: VirtualMemory<DefaultMemoryImpl>, config: SessionConfig) -> Self {
+        SessionStore {
+            sessions: StableBTreeMap::init(memory),
+            ttl: config.ttl.as_nanos().try_into().unwrap_or(u64::MAX),
+            strict: config.strict,
+            max_sessions: config.max_sessions,
+            sweep_from: None,
+        }
+    }
+
+    /// Store a new session after sweeping for expired ones, or fail when the store is full.
+    pub(crate) fn insert(
+        &mut self,
+        id: String,
+        mut session: Session,
+        now: u64,
+    ) -> Result<(), String> {
+        self.sweep(now);
+        if self.sessions.len() >= self.max_sessions {
+            return Err(format!("session limit of {} reached", self.max_sessions));
+        }
+
+        session.expires_at = now.saturating_add(self.ttl);
+        self.sessions.insert(id, session);
+        Ok(())
+    }
+
+    /// Drop the expired sessions among the next [`SWEEP`] ones, continuing where the previous
+    /// sweep stopped so that the cost per call stays bounded and every session is eventually
+    /// checked.
+    fn sweep(&mut self, now: u64) {
+        let start = self.sweep_from.take().unwrap_or_default();
+        let mut batch: Vec<(String, bool)> = self
+            .sessions
+            .range(start..)
+            .take(SWEEP + 1)
+            .map(|(id, session)| (id, session.expires_at <= now))
+            .collect();
+        if batch.len() > SWEEP {
+            self.sweep_from = batch.pop().map(|(id, _)| id);
+        }
+        for (id, expired) in batch {
+            if expired {
+                self.sessions.remove(&id);
+            }
+        }
+    }
+
+    /// Look up a live session and extend its expiry.
+    pub(crate) fn touch(&mut self, id: &str, now: u64) -> Option<Session> {
+        let id = id.to_string();
+        match self.sessions.get(&id) {
+            Some(mut session) if session.expires_at > now => {
+                session.expires_at = now.saturating_add(self.ttl);
+                self.sessions.insert(id, session.clone());
+                Some(session)
+            }
+            Some(_) => {
+                self.sessions.remove(&id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn remove(&mut self, id: &str) -> bool {
+        self.sessions.remove(&id.to_string()).is_some()
+    }
+
+    fn mark_initialized(&mut self, id: &str) {
+        let id = id.to_string();
+        if let Some(mut session) = self.sessions.get(&id) {
+            session.initialized = true;
+            self.sessions.insert(id, session);
+        }
+    }
+
+    fn set_log_level(&mut self, id: &str, level: LoggingLevel) {
+        let id = id.to_string();
+        if let Some(mut session) = self.sessions.get(&id) {
+            session.log_level = Some(level);
+            self.sessions.insert(id, session);
+        }
+    }
+}
+
+pub(crate) fn sessions_enabled() -> bool {
+    SESSIONS.with_borrow(|sessions| sessions.is_some())
+}
+
+fn session_id(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("Mcp-Session-Id"))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+/// Attach the session named by the request's `Mcp-Session-Id` header to `context`.
+///
+/// Returns `context` unchanged when sessions are disabled, and the HTTP error to send back when
+/// the header is missing or names an unknown session.
+pub(crate) fn resume_session(
+    req: &HttpRequest,
+    mut context: Context,
+) -> Result<Context, HttpResponse<'static>> {
+    if !sessions_enabled() {
+        return Ok(context);
+    }
+
+    let strict = SESSIONS.with_borrow(|sessions| sessions.as_ref().is_some_and(|s| s.strict));
+
+    let Some(id) = session_id(req) else {
+        return Err(match strict {
+            true => HttpResponse::builder()
+                .with_status_code(StatusCode::from_u16(400).unwrap())
+                .with_headers(vec![("Content-Type".to_string(), "application/json".to_string())])
+                .with_body(br#"{"jsonrpc": "2.0", "error": {"code": -32600, "message": "Server not initialized"}, "id": null}"#)
+                .build(),
+            false => HttpResponse::builder()
+                .with_status_code(StatusCode::from_u16(400).unwrap())
+                .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+                .with_body(b"Bad Request: missing Mcp-Session-Id header")
+                .build(),
+        });
+    };
+
+    let session = SESSIONS
+        .with_borrow_mut(|sessions| sessions.as_mut()?.touch(&id, now()))
+        .ok_or(session_not_found())?;
+
+    // Without the header, clients are assumed to speak 2025-03-26.
+    let version = req
+        .headers()
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("MCP-Protocol-Version"))
+        .map(|(_, value)| value.trim())
+        .unwrap_or("2025-03-26");
+    if strict && to_value(&session.protocol_version).ok() != Some(Value::from(version)) {
+        return Err(HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(400).unwrap())
+            .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+            .with_body(b"Bad Request: MCP-Protocol-Version does not match the negotiated version")
+            .build());
+    }
+
+    context.session_id = Some(id);
+    context.client_info = Some(session.client_info);
+    context.protocol_version = Some(session.protocol_version);
+    context.log_level = session.log_level;
+    Ok(context)
+}
+
+/// Whether the client of `context` may issue requests other than `ping`.
+///
+/// Always `true` unless strict sessions are enabled and the client has not yet sent
+/// `notifications/initialized`.
+pub(crate) fn is_initialized(context: &Context) -> bool {
+    SESSIONS.with_borrow(|sessions| match (sessions, &context.session_id) {
+        (Some(sessions), Some(id)) if sessions.strict => sessions
+            .sessions
+            .get(id)
+            .is_some_and(|session| session.initialized),
+        _ => true,
+    })
+}
+
+/// Record that the client of `context` sent `notifications/initialized`.
+pub(crate) fn mark_initialized(context: &Context) {
+    if let Some(id) = &context.session_id {
+        SESSIONS.with_borrow_mut(|sessions| {
+            if let Some(sessions) = sessions.as_mut() {
+                sessions.mark_initialized(id);
+            }
+        });
+    }
+}
+
+/// Record the level the client of `context` set with `logging/setLevel`.
+///
+/// Does nothing without a session, so that one client cannot change the level of all others.
+pub(crate) fn set_log_level(context: &Context, level: LoggingLevel) {
+    if let Some(id) = &context.session_id {
+        SESSIONS.with_borrow_mut(|sessions| {
+            if let Some(sessions) = sessions.as_mut() {
+                sessions.set_log_level(id, level);
+            }
+        });
+    }
+}
+
+/// Open a session for a successful `initialize`, returning the id to send to the client.
+///
+/// Returns `Ok(None)` when sessions are disabled.
+pub(crate) async fn start_session(
+    request: &InitializeRequestParam,
+    protocol_version: ProtocolVersion,
+) -> Result<Option<String>, String> {
+    if !sessions_enabled() {
+        return Ok(None);
+    }
+
+    let id = new_session_id().await?;
+    let session = Session {
+        protocol_version,
+        client_info: request.client_info.clone(),
+        client_capabilities: request.capabilities.clone(),
+        expires_at: 0,
+        initialized: false,
+        log_level: None,
+    };
+
+    SESSIONS.with_borrow_mut(|sessions| match sessions.as_mut() {
+        Some(sessions) => sessions.insert(id.clone(), session, now()),
+        None => Ok(()),
+    })?;
+    Ok(Some(id))
+}
+
+/// Handle `DELETE /mcp`, terminating the session named by the `Mcp-Session-Id` header.
+pub(crate) fn end_session(req: &HttpRequest) -> HttpResponse<'static> {
+    let Some(id) = session_id(req) else {
+        return HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(400).unwrap())
+            .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+            .with_body(b"Bad Request: missing Mcp-Session-Id header")
+            .build();
+    };
+
+    match SESSIONS.with_borrow_mut(|sessions| sessions.as_mut().is_some_and(|s| s.remove(&id))) {
+        true => HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(204).unwrap())
+            .build(),
+        false => session_not_found(),
+    }
+}
+
+fn session_not_found() -> HttpResponse<'static> {
+    HttpResponse::builder()
+        .with_status_code(StatusCode::from_u16(404).unwrap())
+        .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+        .with_body(b"Session not found")
+        .build()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> u64 {
+    ic_cdk::api::time()
+}
+
+// Native builds (unit tests) have no system API; fall back to the host clock.
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn new_session_id() -> Result<String, String> {
+    let bytes = ic_cdk::management_canister::raw_rand()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+// Native builds (unit tests) cannot reach the management canister; ids only need to be unique.
+#[cfg(not(target_arch = "wasm32"))]
+async fn new_session_id() -> Result<String, String> {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    Ok(format!(
+        "{:016x}{:016x}",
+        now(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use ic_http_certification::Method;
+    use ic_stable_structures::memory_manager::{MemoryId, MemoryManager};
+
+    fn init() {
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_sessions(
+            manager.get(MemoryId::new(0)),
+            SessionConfig {
+                ttl: Duration::from_nanos(10),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn session() -> Session {
+        Session {
+            protocol_version: ProtocolVersion::V_2025_03_26,
+            client_info: Implementation {
+                name: "foo".to_string(),
+                version: "bar".to_string(),
+                title: None,
+                icons: None,
+                website_url: None,
+            },
+            client_capabilities: ClientCapabilities::default(),
+            expires_at: 0,
+            initialized: false,
+            log_level: None,
+        }
+    }
+
+    #[test]
+    fn test_session_store() {
+        init();
+        SESSIONS.with_borrow_mut(|sessions| {
+            let sessions = sessions.as_mut().unwrap();
+
+            sessions.insert("a".to_string(), session(), 100).unwrap();
+            assert_eq!(sessions.touch("a", 105).map(|s| s.expires_at), Some(115));
+            assert_eq!(sessions.touch("a", 114).map(|s| s.expires_at), Some(124));
+            assert_eq!(sessions.touch("a", 124), None);
+            assert_eq!(sessions.touch("a", 100), None);
+            assert_eq!(sessions.touch("b", 100), None);
+
+            sessions.insert("b".to_string(), session(), 100).unwrap();
+            sessions.insert("c".to_string(), session(), 200).unwrap();
+            assert_eq!(sessions.sessions.len(), 1);
+            assert!(sessions.remove("c"));
+            assert!(!sessions.remove("c"));
+        });
+    }
+
+    #[test]
+    fn test_session_store_bounds() {
+        init();
+        SESSIONS.with_borrow_mut(|sessions| {
+            let sessions = sessions.as_mut().unwrap();
+            sessions.max_sessions = 40;
+
+            for i in 0..40 {
+                sessions.insert(format!("{i:02}"), session(), 100).unwrap();
+            }
+            assert!(sessions.insert("new".to_string(), session(), 105).is_err());
+            sessions.sweep_from = None;
+
+            // Each insert sweeps a bounded batch, so expired sessions are dropped gradually.
+            sessions.insert("a".to_string(), session(), 200).unwrap();
+            assert_eq!(sessions.sessions.len(), 40 - SWEEP as u64 + 1);
+            sessions.insert("b".to_string(), session(), 200).unwrap();
+            sessions.insert("c".to_string(), session(), 200).unwrap();
+            assert_eq!(sessions.sessions.len(), 3);
+        });
+
+        assert_eq!(Session::from_bytes(Cow::Borrowed(b"{")).expires_at, 0);
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let request = |id: Option<&str>| {
+            HttpRequest::builder()
+                .with_method(Method::DELETE)
+                .with_url("/mcp")
+                .with_headers(
+                    id.map(|id| ("mcp-session-id".to_string(), id.to_string()))
+                        .into_iter()
+                        .collect(),
+                )
+                .build()
+        };
+
+        assert_eq!(
+            resume_session(&request(None), Context::default()),
+            Ok(Context::default())
+        );
+        assert_eq!(
+            block_on(start_session(
+                &InitializeRequestParam::default(),
+                ProtocolVersion::V_2025_03_26
+            )),
+            Ok(None)
+        );
+
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_sessions(manager.get(MemoryId::new(0)), SessionConfig::default());
+
+        let id = block_on(start_session(
+            &InitializeRequestParam::default(),
+            ProtocolVersion::V_2025_03_26,
+        ))
+        .unwrap()
+        .unwrap();
+
+        let context = resume_session(&request(Some(&id)), Context::default()).unwrap();
+        assert_eq!(context.session_id, Some(id.clone()));
+        assert_eq!(context.client_info, Some(Implementation::from_build_env()));
+        assert_eq!(context.log_level, None);
+
+        set_log_level(&context, LoggingLevel::Debug);
+        set_log_level(&Context::default(), LoggingLevel::Error);
+        let context = resume_session(&request(Some(&id)), Context::default()).unwrap();
+        assert_eq!(context.log_level, Some(LoggingLevel::Debug));
+
+        assert_eq!(
+            resume_session(&request(None), Context::default())
+                .unwrap_err()
+                .status_code(),
+            StatusCode::from_u16(400).unwrap()
+        );
+        assert_eq!(
+            resume_session(&request(Some("unknown")), Context::default()).unwrap_err(),
+            session_not_found()
+        );
+
+        assert_eq!(
+            end_session(&request(Some(&id))).status_code(),
+            StatusCode::from_u16(204).unwrap()
+        );
+        assert_eq!(end_session(&request(Some(&id))), session_not_found());
+        assert_eq!(
+            end_session(&request(None)).status_code(),
+            StatusCode::from_u16(400).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_strict_session() {
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_sessions(
+            manager.get(MemoryId::new(0)),
+            SessionConfig {
+                strict: true,
+                ..Default::default()
+            },
+        );
+
+        let request = |headers: Vec<(&str, &str)>| {
+            HttpRequest::builder()
+                .with_method(Method::POST)
+                .with_url("/mcp")
+                .with_headers(
+                    headers
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                )
+                .build()
+        };
+
+        assert_eq!(
+            resume_session(&request(vec![]), Context::default()).unwrap_err(),
+            HttpResponse::builder()
+                .with_status_code(StatusCode::from_u16(400).unwrap())
+                .with_headers(vec![("Content-Type".to_string(), "application/json".to_string())])
+                .with_body(br#"{"jsonrpc": "2.0", "error": {"code": -32600, "message": "Server not initialized"}, "id": null}"#)
+                .build()
+        );
+
+        let id = block_on(start_session(
+            &InitializeRequestParam::default(),
+            ProtocolVersion::V_2025_03_26,
+        ))
+        .unwrap()
+        .unwrap();
+
+        let context =
+            resume_session(&request(vec![("Mcp-Session-Id", &id)]), Context::default()).unwrap();
+        assert!(!is_initialized(&context));
+        mark_initialized(&context);
+        assert!(is_initialized(&context));
+
+        assert!(resume_session(
+            &request(vec![
+                ("Mcp-Session-Id", &id),
+                ("MCP-Protocol-Version", "2025-03-26")
+            ]),
+            Context::default()
+        )
+        .is_ok());
+        assert_eq!(
+            resume_session(
+                &request(vec![
+                    ("Mcp-Session-Id", &id),
+                    ("MCP-Protocol-Version", "2025-06-18")
+                ]),
+                Context::default()
+            )
+            .unwrap_err()
+            .status_code(),
+            StatusCode::from_u16(400).unwrap()
+        );
+
+        assert!(is_initialized(&Context::default()));
+    }
+}
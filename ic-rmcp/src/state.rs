@@ -1,3 +1,5 @@
+use crate::certification::CertifiedResponses;
+use crate::session::SessionStore;
 use ic_cdk::management_canister::{
     http_request_with_closure, HttpMethod, HttpRequestArgs, HttpRequestResult,
 };
@@ -7,10 +9,16 @@ use std::cell::RefCell;
 
 thread_local! {
    pub static JWT_SET: RefCell<Option<JwkSet>> = RefCell::default();
+   pub static SESSIONS: RefCell<Option<SessionStore>> = const { RefCell::new(None) };
+   pub static CERTIFICATION: RefCell<Option<CertifiedResponses>> = const { RefCell::new(None) };
+}
+
+pub fn cached_jwks() -> Option<JwkSet> {
+    JWT_SET.with_borrow(|jwt_set| jwt_set.clone())
 }
 
 pub async fn fetch_jwks(jwks_url: &str) -> Result<JwkSet, String> {
-    if let Some(set) = JWT_SET.with_borrow(|jwt_set| jwt_set.clone()) {
+    if let Some(set) = cached_jwks() {
         return Ok(set);
     }
 
@@ -0,0 +1,97 @@
+//! Structured tool output (`outputSchema` and `structuredContent`, protocol `2025-06-18`).
+//!
+//! Declare a tool's schema with [`Tool::with_output_schema`](crate::model::Tool::with_output_schema) and return [`structured_result`]
+//! from [`Handler::call_tool`](crate::Handler::call_tool).
+//! Clients that negotiated an earlier protocol version only receive the text fallback.
+use crate::{
+    model::{CallToolResult, Content, ListToolsResult},
+    Error,
+};
+use rmcp::schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{to_string, to_value};
+
+/// Build a successful tool result carrying `value` as `structuredContent`, with its JSON
+/// serialization as a text fallback.
+///
+/// `value` must serialize to a JSON object, as required for `structuredContent`.
+pub fn structured_result<T: Serialize + JsonSchema>(value: &T) -> Result<CallToolResult, Error> {
+    let structured = to_value(value).map_err(|err| Error::internal_error(err.to_string(), None))?;
+    if !structured.is_object() {
+        return Err(Error::internal_error(
+            "structured content must be a JSON object",
+            None,
+        ));
+    }
+    let json =
+        to_string(&structured).map_err(|err| Error::internal_error(err.to_string(), None))?;
+
+    let mut result = CallToolResult::success(vec![Content::text(json)]);
+    result.structured_content = Some(structured);
+    Ok(result)
+}
+
+/// Remove the `outputSchema` of every tool, for clients older than `2025-06-18`.
+pub(crate) fn strip_output_schemas(result: &mut ListToolsResult) {
+    for tool in result.tools.iter_mut() {
+        tool.output_schema = None;
+    }
+}
+
+/// Remove `structuredContent`, for clients older than `2025-06-18`.
+pub(crate) fn strip_structured_content(result: &mut CallToolResult) {
+    result.structured_content = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::Tool, schema_for_type};
+    use serde_json::{json, Value};
+
+    #[derive(Serialize, JsonSchema)]
+    struct Balance {
+        amount: u64,
+    }
+
+    #[test]
+    fn test_output_schema() {
+        let tool = Tool::new("balance", "Get a balance", schema_for_type::<Balance>())
+            .with_output_schema::<Balance>();
+        let schema = Value::Object(schema_for_type::<Balance>());
+        let value = to_value(&tool).unwrap();
+        assert_eq!(value["outputSchema"], schema);
+        assert_eq!(value["inputSchema"], schema);
+
+        let mut result = ListToolsResult::with_all_items(vec![tool]);
+        strip_output_schemas(&mut result);
+        assert_eq!(
+            to_value(&result).unwrap()["tools"][0].get("outputSchema"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_structured_result() {
+        let mut result = structured_result(&Balance { amount: 5 }).unwrap();
+        assert_eq!(
+            to_value(&result).unwrap(),
+            json!({
+                "content": [{"type": "text", "text": "{\"amount\":5}"}],
+                "structuredContent": {"amount": 5},
+                "isError": false
+            })
+        );
+
+        strip_structured_content(&mut result);
+        assert_eq!(
+            to_value(&result).unwrap(),
+            json!({
+                "content": [{"type": "text", "text": "{\"amount\":5}"}],
+                "isError": false
+            })
+        );
+
+        assert!(structured_result(&vec![1, 2]).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Builder methods for [`Tool`] metadata: annotations and title.
+//!
+//! ```ignore
+//! Tool::new("transfer", "Transfer tokens", schema_for_type::<TransferArgs>())
+//!     .with_title("Transfer")
+//!     .destructive(false)
+//!     .idempotent(true)
+//!     .open_world(false)
+//! ```
+//!
+//! Annotations are hints that clients use to decide which calls need the user's approval.
+//! ic-rmcp also reads them: [`Handler::destructive_scope`](crate::Handler::destructive_scope)
+//! guards tools that [`ToolExt::is_destructive`] reports.
+use crate::model::{Tool, ToolAnnotations};
+
+/// Extension methods for [`Tool`].
+pub trait ToolExt {
+    /// Set a human-readable title, shown by clients instead of the name.
+    fn with_title(self, title: impl Into<String>) -> Self;
+    /// Set `readOnlyHint`: the tool does not modify its environment.
+    fn read_only(self, read_only: bool) -> Self;
+    /// Set `destructiveHint`: the tool may delete or overwrite data, rather than only add to it.
+    fn destructive(self, destructive: bool) -> Self;
+    /// Set `idempotentHint`: repeating a call with the same arguments has no further effect.
+    fn idempotent(self, idempotent: bool) -> Self;
+    /// Set `openWorldHint`: the tool interacts with entities outside the canister, such as
+    /// other canisters or HTTPS outcalls.
+    fn open_world(self, open_world: bool) -> Self;
+    /// Whether the tool is annotated as read-only.
+    fn is_read_only(&self) -> bool;
+    /// Whether the tool may be destructive, following the defaults of the MCP specification:
+    /// tools are destructive unless annotated as read-only or as non-destructive.
+    fn is_destructive(&self) -> bool;
+}
+
+impl ToolExt for Tool {
+    fn with_title(self, title: impl Into<String>) -> Self {
+        annotate(self, |annotations| annotations.title = Some(title.into()))
+    }
+
+    fn read_only(self, read_only: bool) -> Self {
+        annotate(self, |annotations| {
+            annotations.read_only_hint = Some(read_only)
+        })
+    }
+
+    fn destructive(self, destructive: bool) -> Self {
+        annotate(self, |annotations| {
+            annotations.destructive_hint = Some(destructive)
+        })
+    }
+
+    fn idempotent(self, idempotent: bool) -> Self {
+        annotate(self, |annotations| {
+            annotations.idempotent_hint = Some(idempotent)
+        })
+    }
+
+    fn open_world(self, open_world: bool) -> Self {
+        annotate(self, |annotations| {
+            annotations.open_world_hint = Some(open_world)
+        })
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|annotations| annotations.read_only_hint)
+            .unwrap_or(false)
+    }
+
+    fn is_destructive(&self) -> bool {
+        !self.is_read_only()
+            && self
+                .annotations
+                .as_ref()
+                .is_none_or(ToolAnnotations::is_destructive)
+    }
+}
+
+fn annotate(mut tool: Tool, update: impl FnOnce(&mut ToolAnnotations)) -> Tool {
+    update(
+        tool.annotations
+            .get_or_insert_with(ToolAnnotations::default),
+    );
+    tool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::EmptyObject, schema_for_type};
+    use serde_json::{json, to_value};
+
+    fn tool() -> Tool {
+        Tool::new(
+            "transfer",
+            "Transfer tokens",
+            schema_for_type::<EmptyObject>(),
+        )
+    }
+
+    #[test]
+    fn test_annotations() {
+        let tool = tool()
+            .with_title("Transfer")
+            .destructive(false)
+            .idempotent(true)
+            .open_world(false);
+        assert_eq!(
+            to_value(&tool).unwrap()["annotations"],
+            json!({
+                "title": "Transfer",
+                "destructiveHint": false,
+                "idempotentHint": true,
+                "openWorldHint": false
+            })
+        );
+        assert!(!tool.is_read_only());
+        assert!(!tool.is_destructive());
+    }
+
+    #[test]
+    fn test_is_destructive() {
+        assert!(tool().is_destructive());
+        assert!(tool().with_title("Transfer").is_destructive());
+        assert!(tool().read_only(false).is_destructive());
+        assert!(!tool().read_only(true).is_destructive());
+        assert!(!tool().destructive(false).is_destructive());
+        assert!(tool().destructive(true).is_destructive());
+    }
+}
@@ -0,0 +1,468 @@
+//! Validation of tool arguments against a tool's `inputSchema`.
+//!
+//! Covers the JSON Schema keywords emitted by [`schema_for_type`](crate::schema_for_type):
+//! `type`, `nullable`, `enum`, `const`, `properties`, `required`, `additionalProperties`,
+//! `items`, `$ref`, `allOf`/`anyOf`/`oneOf`/`not` and the numeric, length and size bounds.
+//! `pattern` and `format` are not enforced, which keeps regex engines out of the canister.
+use crate::{
+    model::{JsonObject, Tool},
+    Error,
+};
+use serde_json::{json, Map, Number, Value};
+
+/// `$ref` chains deeper than this are treated as cycles and stop validation.
+const MAX_DEPTH: usize = 64;
+
+struct Violation {
+    pointer: String,
+    message: String,
+}
+
+/// Check `arguments` against the `inputSchema` of `tool`; missing arguments are treated as `{}`.
+///
+/// On failure, returns an `invalid_params` error whose message lists every failing JSON pointer,
+/// in pointer order, and whose data holds them as `{"errors": [{"pointer", "message"}]}`.
+pub(crate) fn check_arguments(tool: &Tool, arguments: Option<&JsonObject>) -> Result<(), Error> {
+    let root = Value::Object(tool.input_schema.as_ref().clone());
+    let arguments = Value::Object(arguments.cloned().unwrap_or_default());
+
+    let mut validator = Validator {
+        root: &root,
+        violations: Vec::new(),
+    };
+    validator.check(&root, &arguments, String::new(), 0);
+    if validator.violations.is_empty() {
+        return Ok(());
+    }
+    validator
+        .violations
+        .sort_by(|a, b| a.pointer.cmp(&b.pointer));
+
+    let summary: Vec<String> = validator
+        .violations
+        .iter()
+        .map(|v| match v.pointer.as_str() {
+            "" => format!("arguments {}", v.message),
+            pointer => format!("{pointer} {}", v.message),
+        })
+        .collect();
+    let errors: Vec<Value> = validator
+        .violations
+        .iter()
+        .map(|v| json!({"pointer": v.pointer, "message": v.message}))
+        .collect();
+    Err(Error::invalid_params(
+        format!(
+            "invalid arguments to tool {}: {}",
+            tool.name,
+            summary.join("; ")
+        ),
+        Some(json!({ "errors": errors })),
+    ))
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Validator<'a> {
+    fn fail(&mut self, pointer: &str, message: impl Into<String>) {
+        self.violations.push(Violation {
+            pointer: pointer.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Whether `value` satisfies `schema`, without recording violations.
+    fn matches(&self, schema: &'a Value, value: &Value, depth: usize) -> bool {
+        let mut probe = Validator {
+            root: self.root,
+            violations: Vec::new(),
+        };
+        probe.check(schema, value, String::new(), depth);
+        probe.violations.is_empty()
+    }
+
+    fn check(&mut self, schema: &'a Value, value: &Value, pointer: String, depth: usize) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.fail(&pointer, "is not allowed"),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+        if depth > MAX_DEPTH {
+            return;
+        }
+
+        if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+            return;
+        }
+
+        if let Some(target) = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|r| r.strip_prefix('#'))
+            .and_then(|r| self.root.pointer(r))
+        {
+            self.check(target, value, pointer.clone(), depth + 1);
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let types: Vec<&str> = match expected {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+                return self.fail(&pointer, format!("must be of type {}", types.join(" or ")));
+            }
+        }
+
+        if let Some(Value::Array(options)) = schema.get("enum") {
+            if !options.contains(value) {
+                self.fail(
+                    &pointer,
+                    format!("must be one of {}", Value::Array(options.clone())),
+                );
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                self.fail(&pointer, format!("must be {constant}"));
+            }
+        }
+
+        for sub in array(schema, "allOf") {
+            self.check(sub, value, pointer.clone(), depth + 1);
+        }
+        let any_of = array(schema, "anyOf");
+        if !any_of.is_empty() && !any_of.iter().any(|s| self.matches(s, value, depth + 1)) {
+            self.fail(&pointer, "must match at least one schema in anyOf");
+        }
+        let one_of = array(schema, "oneOf");
+        if !one_of.is_empty()
+            && one_of
+                .iter()
+                .filter(|s| self.matches(s, value, depth + 1))
+                .count()
+                != 1
+        {
+            self.fail(&pointer, "must match exactly one schema in oneOf");
+        }
+        if let Some(not) = schema.get("not") {
+            if self.matches(not, value, depth + 1) {
+                self.fail(&pointer, "must not match the schema in not");
+            }
+        }
+
+        match value {
+            Value::Number(n) => self.check_number(schema, n, &pointer),
+            Value::String(s) => {
+                let len = s.chars().count() as f64;
+                if let Some(min) = bound(schema, "minLength").filter(|min| len < *min) {
+                    self.fail(&pointer, format!("must be at least {min} characters long"));
+                }
+                if let Some(max) = bound(schema, "maxLength").filter(|max| len > *max) {
+                    self.fail(&pointer, format!("must be at most {max} characters long"));
+                }
+            }
+            Value::Array(items) => self.check_array(schema, items, &pointer, depth),
+            Value::Object(object) => self.check_object(schema, object, &pointer, depth),
+            _ => {}
+        }
+    }
+
+    fn check_number(&mut self, schema: &Map<String, Value>, number: &Number, pointer: &str) {
+        let n = number.as_f64().unwrap_or_default();
+        if let Some(min) = bound(schema, "minimum").filter(|min| n < *min) {
+            self.fail(pointer, format!("must be >= {min}"));
+        }
+        if let Some(max) = bound(schema, "maximum").filter(|max| n > *max) {
+            self.fail(pointer, format!("must be <= {max}"));
+        }
+        if let Some(min) = bound(schema, "exclusiveMinimum").filter(|min| n <= *min) {
+            self.fail(pointer, format!("must be > {min}"));
+        }
+        if let Some(max) = bound(schema, "exclusiveMaximum").filter(|max| n >= *max) {
+            self.fail(pointer, format!("must be < {max}"));
+        }
+        if let Some(Value::Number(step)) = schema.get("multipleOf") {
+            if !is_multiple(number, step) {
+                self.fail(pointer, format!("must be a multiple of {step}"));
+            }
+        }
+    }
+
+    fn check_array(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        items: &[Value],
+        pointer: &str,
+        depth: usize,
+    ) {
+        let len = items.len() as f64;
+        if let Some(min) = bound(schema, "minItems").filter(|min| len < *min) {
+            self.fail(pointer, format!("must have at least {min} items"));
+        }
+        if let Some(max) = bound(schema, "maxItems").filter(|max| len > *max) {
+            self.fail(pointer, format!("must have at most {max} items"));
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true))
+            && items
+                .iter()
+                .enumerate()
+                .any(|(i, item)| items[..i].contains(item))
+        {
+            self.fail(pointer, "must not contain duplicate items");
+        }
+
+        match schema.get("items") {
+            // Tuple form: one schema per position, then `additionalItems`.
+            Some(Value::Array(schemas)) => {
+                for (i, item) in items.iter().enumerate() {
+                    let item_schema = schemas.get(i).or_else(|| schema.get("additionalItems"));
+                    if let Some(item_schema) = item_schema {
+                        self.check(item_schema, item, format!("{pointer}/{i}"), depth + 1);
+                    }
+                }
+            }
+            Some(item_schema) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.check(item_schema, item, format!("{pointer}/{i}"), depth + 1);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn check_object(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        object: &Map<String, Value>,
+        pointer: &str,
+        depth: usize,
+    ) {
+        for name in array(schema, "required").iter().filter_map(|n| n.as_str()) {
+            if !object.contains_key(name) {
+                self.fail(&format!("{pointer}/{}", escape(name)), "is required");
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, value) in object {
+            let child = format!("{pointer}/{}", escape(name));
+            match properties.and_then(|p| p.get(name)) {
+                Some(property) => self.check(property, value, child, depth + 1),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => self.fail(&child, "is not allowed"),
+                    Some(additional) => self.check(additional, value, child, depth + 1),
+                    None => {}
+                },
+            }
+        }
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn array<'a>(schema: &'a Map<String, Value>, key: &str) -> &'a [Value] {
+    schema
+        .get(key)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn bound(schema: &Map<String, Value>, key: &str) -> Option<f64> {
+    schema.get(key).and_then(Value::as_f64)
+}
+
+/// Whether `n` is a multiple of `step`, exactly for integers and within a relative tolerance for
+/// floats so that e.g. 0.3 counts as a multiple of 0.1. Non-positive steps are ignored.
+fn is_multiple(n: &Number, step: &Number) -> bool {
+    if let (Some(n), Some(step)) = (n.as_i64(), step.as_i64()) {
+        return step <= 0 || n % step == 0;
+    }
+    if let (Some(n), Some(step)) = (n.as_u64(), step.as_u64()) {
+        return step == 0 || n % step == 0;
+    }
+    let (n, step) = (
+        n.as_f64().unwrap_or_default(),
+        step.as_f64().unwrap_or_default(),
+    );
+    if step <= 0.0 {
+        return true;
+    }
+    let quotient = n / step;
+    (quotient - quotient.round()).abs() <= f64::EPSILON * quotient.abs().max(1.0) * 4.0
+}
+
+/// Escape a property name for use as a JSON pointer segment (RFC 6901).
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema_for_type;
+    use rmcp::schemars::JsonSchema;
+    use serde::Deserialize;
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    enum Unit {
+        Celsius,
+        Fahrenheit,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    struct Request {
+        count: u8,
+        unit: Unit,
+        label: Option<String>,
+        points: Vec<Point>,
+    }
+
+    fn check(arguments: Value) -> Result<(), Error> {
+        let tool = Tool::new("plot", "Plot points", schema_for_type::<Request>());
+        check_arguments(&tool, arguments.as_object())
+    }
+
+    #[test]
+    fn test_valid_arguments() {
+        assert_eq!(
+            check(json!({"count": 2, "unit": "Celsius", "points": [{"x": 1, "y": 2}]})),
+            Ok(())
+        );
+        assert_eq!(
+            check(json!({"count": 0, "unit": "Fahrenheit", "label": null, "points": []})),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let err = check(json!({
+            "count": -1,
+            "unit": "Kelvin",
+            "points": [{"x": 1}, {"x": 1.5, "y": "2"}],
+            "extra/key": true
+        }))
+        .unwrap_err();
+
+        let pointers: Vec<&str> = err.data.as_ref().unwrap()["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["pointer"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            pointers,
+            vec![
+                "/count",
+                "/extra~1key",
+                "/points/0/y",
+                "/points/1/x",
+                "/points/1/y",
+                "/unit"
+            ]
+        );
+        assert_eq!(
+            err.message,
+            "invalid arguments to tool plot: /count must be >= 0; /extra~1key is not allowed; \
+             /points/0/y is required; /points/1/x must be of type integer; \
+             /points/1/y must be of type integer; /unit must be one of [\"Celsius\",\"Fahrenheit\"]"
+        );
+
+        assert_eq!(
+            check(json!({})).unwrap_err().message,
+            "invalid arguments to tool plot: /count is required; /points is required; \
+             /unit is required"
+        );
+        assert_eq!(
+            check_arguments(&Tool::new("empty", "Empty", JsonObject::new()), None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_keywords() {
+        let schema = |schema: Value| Tool::new("t", "t", schema.as_object().unwrap().clone());
+        let arguments = |value: Value| value.as_object().cloned();
+
+        let tool = schema(json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 2, "maxLength": 3},
+                "tags": {"type": "array", "uniqueItems": true, "maxItems": 2},
+                "ratio": {"type": "number", "exclusiveMinimum": 0, "multipleOf": 0.5},
+                "either": {"anyOf": [{"type": "string"}, {"type": "integer"}]}
+            },
+            "additionalProperties": {"type": "boolean"}
+        }));
+        assert_eq!(
+            check_arguments(
+                &tool,
+                arguments(
+                    json!({"name": "abc", "tags": [1, 2], "ratio": 1.5, "either": 3, "flag": true})
+                )
+                .as_ref()
+            ),
+            Ok(())
+        );
+
+        let err = check_arguments(
+            &tool,
+            arguments(
+                json!({"name": "a", "tags": [1, 1, 2], "ratio": 0, "either": true, "flag": 1}),
+            )
+            .as_ref(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.message,
+            "invalid arguments to tool t: /either must match at least one schema in anyOf; \
+             /flag must be of type boolean; /name must be at least 2 characters long; \
+             /ratio must be > 0; /tags must have at most 2 items; \
+             /tags must not contain duplicate items"
+        );
+    }
+
+    #[test]
+    fn test_multiple_of() {
+        let multiple = |n: Value, step: Value| match (n, step) {
+            (Value::Number(n), Value::Number(step)) => is_multiple(&n, &step),
+            _ => unreachable!(),
+        };
+        assert!(multiple(json!(0.3), json!(0.1)));
+        assert!(multiple(json!(4.35), json!(0.01)));
+        assert!(!multiple(json!(0.35), json!(0.1)));
+        assert!(multiple(json!(-6), json!(3)));
+        assert!(!multiple(json!(u64::MAX), json!(2)));
+        assert!(multiple(json!(9007199254740993u64), json!(1)));
+        assert!(!multiple(json!(9007199254740993u64), json!(7)));
+    }
+}
@@ -2,10 +2,10 @@ use futures::executor::block_on;
 use ic_http_certification::{HttpRequest, HttpResponse, Method, StatusCode};
 use ic_rmcp::*;
 use rmcp::handler::server::tool::schema_for_type;
-use rmcp::{model::*, Error};
+use rmcp::{model::*, ErrorData as Error};
 use schemars::JsonSchema;
-use serde::Deserialize;
-use serde_json::{from_value, Value};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json, Value};
 
 struct MagicSum;
 
@@ -15,13 +15,26 @@ struct MagicSumRequest {
     b: f64,
 }
 
+#[derive(Deserialize, JsonSchema)]
+struct ExplainRequest {
+    /// Audience of the explanation
+    audience: Option<String>,
+}
+
 impl Handler for MagicSum {
     fn get_info(&self, _: Context) -> ServerInfo {
         ServerInfo {
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_prompts()
+                .enable_resources()
+                .enable_tools()
+                .build(),
             server_info: Implementation {
                 name: "Magic sum calculator".to_string(),
                 version: "1.0.0".to_string(),
+                title: None,
+                icons: None,
+                website_url: None,
             },
             instructions: Some(String::from("This server provides a `calculate_magic_sum` tool returning a magic sum between two numbers")),
             ..Default::default()
@@ -45,7 +58,7 @@ impl Handler for MagicSum {
 
     async fn call_tool(
         &self,
-        _: Context,
+        context: Context,
         request: CallToolRequestParam,
     ) -> Result<CallToolResult, Error> {
         match request.name.as_ref() {
@@ -53,14 +66,142 @@ impl Handler for MagicSum {
                 None => Err(Error::invalid_params("invalid arguments to tool add", None)),
                 Some(data) => match from_value::<MagicSumRequest>(Value::Object(data)) {
                     Err(_) => Err(Error::invalid_params("invalid arguments to tool add", None)),
-                    Ok(args) => Ok(CallToolResult::success(
-                        Content::text(format!("{:.2}", args.a + 3.0 * args.b)).into_contents(),
-                    )),
+                    Ok(args) => {
+                        context.log(LoggingLevel::Info, json!({"a": args.a, "b": args.b}));
+                        context.report_progress(1, Some(1), Some("summed".to_string()));
+                        Ok(CallToolResult::success(
+                            Content::text(format!("{:.2}", args.a + 3.0 * args.b)).into_contents(),
+                        ))
+                    }
                 },
             },
             _ => Err(Error::invalid_params("not found tool", None)),
         }
     }
+
+    async fn list_resources(
+        &self,
+        _: Context,
+        _: Option<PaginatedRequestParam>,
+    ) -> Result<ListResourcesResult, Error> {
+        Ok(ListResourcesResult {
+            next_cursor: None,
+            resources: vec![RawResource::new("magic://coefficient", "coefficient").no_annotation()],
+        })
+    }
+
+    async fn list_prompts(
+        &self,
+        _: Context,
+        _: Option<PaginatedRequestParam>,
+    ) -> Result<ListPromptsResult, Error> {
+        Ok(ListPromptsResult {
+            next_cursor: None,
+            prompts: vec![Prompt::new(
+                "explain_magic_sum",
+                Some("Explain how the magic sum is calculated"),
+                Some(prompt_arguments_for_type::<ExplainRequest>()?),
+            )],
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        _: Context,
+        request: GetPromptRequestParam,
+    ) -> Result<GetPromptResult, Error> {
+        match request.name.as_str() {
+            "explain_magic_sum" => {
+                let args = from_value::<ExplainRequest>(Value::Object(
+                    request.arguments.unwrap_or_default(),
+                ))
+                .map_err(|_| Error::invalid_params("invalid arguments to prompt", None))?;
+
+                Ok(GetPromptResult {
+                    description: None,
+                    messages: vec![PromptMessage::new_text(
+                        PromptMessageRole::User,
+                        format!(
+                            "Explain to {} that the magic sum of a and b is a + 3 * b.",
+                            args.audience.as_deref().unwrap_or("me")
+                        ),
+                    )],
+                })
+            }
+            _ => Err(Error::invalid_params("not found prompt", None)),
+        }
+    }
+
+    fn completions(&self) -> bool {
+        true
+    }
+
+    async fn complete(
+        &self,
+        _: Context,
+        request: CompleteRequestParam,
+    ) -> Result<CompleteResult, Error> {
+        let values: Vec<String> = match (&request.r#ref, request.argument.name.as_str()) {
+            (Reference::Prompt(prompt), "audience") if prompt.name == "explain_magic_sum" => {
+                ["a child", "an engineer", "a mathematician"]
+                    .into_iter()
+                    .filter(|v| v.starts_with(&request.argument.value))
+                    .map(str::to_string)
+                    .collect()
+            }
+            _ => vec![],
+        };
+
+        Ok(CompleteResult {
+            completion: CompletionInfo {
+                total: Some(values.len() as u32),
+                values,
+                has_more: Some(false),
+            },
+        })
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _: Context,
+        _: Option<PaginatedRequestParam>,
+    ) -> Result<ListResourceTemplatesResult, Error> {
+        Ok(ListResourceTemplatesResult {
+            next_cursor: None,
+            resource_templates: vec![RawResourceTemplate {
+                uri_template: "magic://scale/{factor}".to_string(),
+                name: "scale".to_string(),
+                title: None,
+                description: Some("The coefficient multiplied by a factor".to_string()),
+                mime_type: None,
+            }
+            .no_annotation()],
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        context: Context,
+        request: ReadResourceRequestParam,
+    ) -> Result<ReadResourceResult, Error> {
+        let (text, mime_type) = match (context.resource_template.as_deref(), request.uri.as_str()) {
+            (Some("magic://scale/{factor}"), _) => (
+                format!("{:.2}", 3.0 * context.uri_params.parse::<f64>("factor")?),
+                Some("text/plain".to_string()),
+            ),
+            (None, "magic://coefficient") => ("3".to_string(), None),
+            _ => return Err(Error::resource_not_found("not found resource", None)),
+        };
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                meta: None,
+                mime_type,
+                text,
+            }],
+        })
+    }
 }
 
 #[test]
@@ -118,7 +259,7 @@ fn test_initialization() {
                     "Content-Type".to_string(),
                     "application/json".to_string()
                 )])
-                .with_body(br#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"Magic sum calculator","version":"1.0.0"},"instructions":"This server provides a `calculate_magic_sum` tool returning a magic sum between two numbers"}}"#)
+                .with_body(br#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-03-26","capabilities":{"logging":{},"completions":{},"prompts":{},"resources":{},"tools":{}},"serverInfo":{"name":"Magic sum calculator","version":"1.0.0"},"instructions":"This server provides a `calculate_magic_sum` tool returning a magic sum between two numbers"}}"#)
                 .build()
         );
 
@@ -248,7 +389,7 @@ fn test_tools() {
 }
 
 #[test]
-fn test_unsupported_method() {
+fn test_resources() {
     assert_eq!(
         block_on(
             MagicSum {}.handle(
@@ -262,7 +403,7 @@ fn test_unsupported_method() {
                             "id": 1,
                             "method": "resources/list",
                             "params": {}
-                        }    
+                            }    
                 "#
                     )
                     .build(),
@@ -276,14 +417,181 @@ fn test_unsupported_method() {
                 "application/json".to_string()
             )])
             .with_body(
-                br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}"#
+                br#"{"jsonrpc":"2.0","id":1,"result":{"resources":[{"uri":"magic://coefficient","name":"coefficient"}]}}"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "resources/read",
+                            "params": {
+                                "uri": "magic://coefficient"
+                            }
+                            }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"result":{"contents":[{"uri":"magic://coefficient","text":"3"}]}}"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "resources/read",
+                            "params": {
+                                "uri": "magic://unknown"
+                            }
+                            }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32002,"message":"not found resource"}}"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "resources/templates/list"
+                            }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"result":{"resourceTemplates":[{"uriTemplate":"magic://scale/{factor}","name":"scale","description":"The coefficient multiplied by a factor"}]}}"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "resources/read",
+                            "params": {
+                                "uri": "magic://scale/1.5"
+                            }
+                            }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"result":{"contents":[{"uri":"magic://scale/1.5","mimeType":"text/plain","text":"4.50"}]}}"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "resources/read",
+                            "params": {
+                                "uri": "magic://scale/abc"
+                            }
+                            }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"invalid uri parameter `factor`"}}"#
             )
             .build()
     );
 }
 
 #[test]
-fn test_batch() {
+fn test_prompts() {
     assert_eq!(
         block_on(
             MagicSum {}.handle(
@@ -291,21 +599,53 @@ fn test_batch() {
                     .with_method(Method::POST)
                     .with_url("/mcp")
                     .with_body(
-                        br#"[
+                        br#"
                             {
                             "jsonrpc": "2.0",
                             "id": 1,
-                            "method": "prompts/list",
-                            "params": {}
-                        } ,{
+                            "method": "prompts/list"
+                            }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"result":{"prompts":[{"name":"explain_magic_sum","description":"Explain how the magic sum is calculated","arguments":[{"name":"audience","description":"Audience of the explanation","required":false}]}]}}"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
                             "jsonrpc": "2.0",
-                            "id": "123",
-                            "method": "ping"
+                            "id": 1,
+                            "method": "prompts/get",
+                            "params": {
+                                "name": "explain_magic_sum",
+                                "arguments": {
+                                    "audience": "a child"
+                                }
                             }
-                        ]   
+                            }    
                 "#
                     )
-                    .build(), |_| true
+                    .build(),
+                |_| true
             )
         ),
         HttpResponse::builder()
@@ -315,8 +655,862 @@ fn test_batch() {
                 "application/json".to_string()
             )])
             .with_body(
-                br#"[{"error":{"code":-32601,"message":"Method not found"},"id":1,"jsonrpc":"2.0"},{"id":"123","jsonrpc":"2.0","result":{}}]"#
+                br#"{"jsonrpc":"2.0","id":1,"result":{"messages":[{"role":"user","content":{"type":"text","text":"Explain to a child that the magic sum of a and b is a + 3 * b."}}]}}"#
             )
             .build()
     );
 }
+
+#[test]
+fn test_completion() {
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "completion/complete",
+                            "params": {
+                                "ref": {
+                                    "type": "ref/prompt",
+                                    "name": "explain_magic_sum"
+                                },
+                                "argument": {
+                                    "name": "audience",
+                                    "value": "an"
+                                }
+                            }
+                            }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"result":{"completion":{"values":["an engineer"],"total":1,"hasMore":false}}}"#
+            )
+            .build()
+    );
+}
+
+#[test]
+fn test_logging() {
+    let call = |accept: &str| {
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_headers(vec![("Accept".to_string(), accept.to_string())])
+                    .with_body(
+                        br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"calculate_magic_sum","arguments":{"a":1,"b":2}}}"#,
+                    )
+                    .build(),
+                |_| true,
+            ),
+        )
+    };
+
+    assert_eq!(
+        call("application/json, text/event-stream"),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "text/event-stream".to_string()
+            )])
+            .with_body(
+                br#"event: message
+data: {"jsonrpc":"2.0","method":"notifications/message","params":{"data":{"a":1.0,"b":2.0},"level":"info"}}
+
+event: message
+data: {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"7.00"}],"isError":false}}
+
+"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        call("application/json"),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"7.00"}],"isError":false}}"#
+            )
+            .build()
+    );
+
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"{"jsonrpc":"2.0","id":1,"method":"logging/setLevel","params":{"level":"warning"}}"#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(br#"{"jsonrpc":"2.0","id":1,"result":{}}"#)
+            .build()
+    );
+
+    // Without sessions, the level is not kept for later requests.
+    assert_eq!(
+        call("application/json, text/event-stream").headers(),
+        vec![("Content-Type".to_string(), "text/event-stream".to_string())]
+    );
+}
+
+#[test]
+fn test_progress() {
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_headers(vec![(
+                        "Accept".to_string(),
+                        "application/json, text/event-stream".to_string()
+                    )])
+                    .with_body(
+                        br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"_meta":{"progressToken":"abc"},"name":"calculate_magic_sum","arguments":{"a":1,"b":2}}}"#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "text/event-stream".to_string()
+            )])
+            .with_body(
+                br#"event: message
+data: {"jsonrpc":"2.0","method":"notifications/message","params":{"data":{"a":1.0,"b":2.0},"level":"info"}}
+
+event: message
+data: {"jsonrpc":"2.0","method":"notifications/progress","params":{"message":"summed","progress":1.0,"progressToken":"abc","total":1.0}}
+
+event: message
+data: {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"7.00"}],"isError":false}}
+
+"#
+            )
+            .build()
+    );
+}
+
+#[test]
+fn test_unsupported_method() {
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "resources/subscribe",
+                            "params": {
+                                "uri": "magic://coefficient"
+                            }
+                        }    
+                "#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}"#
+            )
+            .build()
+    );
+}
+
+#[test]
+fn test_batch() {
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"[
+                            {
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "method": "resources/unsubscribe",
+                            "params": {
+                                "uri": "magic://coefficient"
+                            }
+                        } ,{
+                            "jsonrpc": "2.0",
+                            "id": "123",
+                            "method": "ping"
+                            }
+                        ]   
+                "#
+                    )
+                    .build(), |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"[{"error":{"code":-32601,"message":"Method not found"},"id":1,"jsonrpc":"2.0"},{"id":"123","jsonrpc":"2.0","result":{}}]"#
+            )
+            .build()
+    );
+}
+
+#[test]
+fn test_batch_initialize() {
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(
+                        br#"[
+                            {"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26","capabilities":{},"clientInfo":{"name":"foo","version":"bar"}}},
+                            {"jsonrpc":"2.0","method":"notifications/initialized"},
+                            {"jsonrpc":"2.0","id":2,"method":"ping"}
+                        ]"#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"[{"error":{"code":-32600,"message":"Invalid Request: initialize must not be part of a batch"},"id":1,"jsonrpc":"2.0"},{"error":{"code":-32600,"message":"Invalid Request: initialize must not be part of a batch"},"id":2,"jsonrpc":"2.0"}]"#
+            )
+            .build()
+    );
+}
+
+#[test]
+fn test_batch_event_stream() {
+    assert_eq!(
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_headers(vec![(
+                        "Accept".to_string(),
+                        "application/json, text/event-stream".to_string()
+                    )])
+                    .with_body(
+                        br#"[
+                            {"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"calculate_magic_sum","arguments":{"a":1,"b":2}}},
+                            {"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"calculate_magic_sum","arguments":{"a":3,"b":4}}}
+                        ]"#
+                    )
+                    .build(),
+                |_| true
+            )
+        ),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "text/event-stream".to_string()
+            )])
+            .with_body(
+                br#"event: message
+data: {"jsonrpc":"2.0","method":"notifications/message","params":{"data":{"a":1.0,"b":2.0},"level":"info"}}
+
+event: message
+data: {"jsonrpc":"2.0","method":"notifications/message","params":{"data":{"a":3.0,"b":4.0},"level":"info"}}
+
+event: message
+data: [{"id":1,"jsonrpc":"2.0","result":{"content":[{"text":"7.00","type":"text"}],"isError":false}},{"id":2,"jsonrpc":"2.0","result":{"content":[{"text":"15.00","type":"text"}],"isError":false}}]
+
+"#
+            )
+            .build()
+    );
+
+    // Every event carries a JSON-RPC 2.0 message, or a batch of them.
+    let body = block_on(
+        MagicSum {}.handle(
+            &HttpRequest::builder()
+                .with_method(Method::POST)
+                .with_url("/mcp")
+                .with_headers(vec![(
+                    "Accept".to_string(),
+                    "application/json, text/event-stream".to_string(),
+                )])
+                .with_body(
+                    br#"[{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"calculate_magic_sum","arguments":{"a":1,"b":2}}}]"#,
+                )
+                .build(),
+            |_| true,
+        ),
+    )
+    .body()
+    .to_vec();
+    for data in String::from_utf8(body)
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+    {
+        let messages = match serde_json::from_str::<Value>(data).unwrap() {
+            Value::Array(batch) => batch,
+            message => vec![message],
+        };
+        for message in messages {
+            assert_eq!(message["jsonrpc"], "2.0", "{message}");
+        }
+    }
+}
+
+#[test]
+fn test_sessions() {
+    use ic_stable_structures::memory_manager::{MemoryId, MemoryManager};
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    let manager = MemoryManager::init(DefaultMemoryImpl::default());
+    init_sessions(manager.get(MemoryId::new(0)), SessionConfig::default());
+
+    let post = |session: Option<&str>, body: &'static [u8]| {
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_headers(
+                        session
+                            .map(|id| ("Mcp-Session-Id".to_string(), id.to_string()))
+                            .into_iter()
+                            .collect(),
+                    )
+                    .with_body(body)
+                    .build(),
+                |_| true,
+            ),
+        )
+    };
+
+    let initialized = post(
+        None,
+        br#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26","capabilities":{},"clientInfo":{"name":"inspector","version":"1.0"}}}"#,
+    );
+    assert_eq!(
+        initialized.status_code(),
+        StatusCode::from_u16(200).unwrap()
+    );
+    let session = initialized
+        .headers()
+        .iter()
+        .find(|(key, _)| key == "Mcp-Session-Id")
+        .map(|(_, value)| value.clone())
+        .unwrap();
+
+    let list_tools = br#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#;
+    assert_eq!(
+        post(None, list_tools),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(400).unwrap())
+            .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+            .with_body(b"Bad Request: missing Mcp-Session-Id header")
+            .build()
+    );
+    assert_eq!(
+        post(Some(&session), list_tools).status_code(),
+        StatusCode::from_u16(200).unwrap()
+    );
+
+    let delete = |id: &str| {
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::DELETE)
+                    .with_url("/mcp")
+                    .with_headers(vec![("Mcp-Session-Id".to_string(), id.to_string())])
+                    .build(),
+                |_| true,
+            ),
+        )
+    };
+    assert_eq!(
+        delete(&session).status_code(),
+        StatusCode::from_u16(204).unwrap()
+    );
+    assert_eq!(
+        delete(&session).status_code(),
+        StatusCode::from_u16(404).unwrap()
+    );
+    assert_eq!(
+        post(Some(&session), list_tools),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(404).unwrap())
+            .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+            .with_body(b"Session not found")
+            .build()
+    );
+}
+
+#[test]
+fn test_strict_lifecycle() {
+    use ic_stable_structures::memory_manager::{MemoryId, MemoryManager};
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    let manager = MemoryManager::init(DefaultMemoryImpl::default());
+    init_sessions(
+        manager.get(MemoryId::new(0)),
+        SessionConfig {
+            strict: true,
+            ..Default::default()
+        },
+    );
+
+    let post = |headers: Vec<(&str, &str)>, body: &'static [u8]| {
+        block_on(
+            MagicSum {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_headers(
+                        headers
+                            .into_iter()
+                            .map(|(k, v)| (k.to_string(), v.to_string()))
+                            .collect(),
+                    )
+                    .with_body(body)
+                    .build(),
+                |_| true,
+            ),
+        )
+    };
+    let list_tools = br#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#;
+
+    assert_eq!(
+        post(vec![], list_tools),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(400).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(br#"{"jsonrpc": "2.0", "error": {"code": -32600, "message": "Server not initialized"}, "id": null}"#)
+            .build()
+    );
+
+    let session = post(
+        vec![],
+        br#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18","capabilities":{},"clientInfo":{"name":"inspector","version":"1.0"}}}"#,
+    )
+    .headers()
+    .iter()
+    .find(|(key, _)| key == "Mcp-Session-Id")
+    .map(|(_, value)| value.clone())
+    .unwrap();
+    let headers = || {
+        vec![
+            ("Mcp-Session-Id", session.as_str()),
+            ("MCP-Protocol-Version", "2025-06-18"),
+        ]
+    };
+
+    assert_eq!(
+        post(headers(), list_tools),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(200).unwrap())
+            .with_headers(vec![(
+                "Content-Type".to_string(),
+                "application/json".to_string()
+            )])
+            .with_body(
+                br#"{"jsonrpc":"2.0","id":2,"error":{"code":-32600,"message":"Server not initialized"}}"#
+            )
+            .build()
+    );
+    assert_eq!(
+        post(headers(), br#"{"jsonrpc":"2.0","id":3,"method":"ping"}"#).body(),
+        br#"{"jsonrpc":"2.0","id":3,"result":{}}"#
+    );
+
+    assert_eq!(
+        post(
+            headers(),
+            br#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#
+        )
+        .status_code(),
+        StatusCode::from_u16(202).unwrap()
+    );
+    assert_eq!(
+        post(headers(), list_tools).status_code(),
+        StatusCode::from_u16(200).unwrap()
+    );
+    assert!(!String::from_utf8_lossy(post(headers(), list_tools).body()).contains("error"));
+
+    assert_eq!(
+        post(vec![("Mcp-Session-Id", session.as_str())], list_tools),
+        HttpResponse::builder()
+            .with_status_code(StatusCode::from_u16(400).unwrap())
+            .with_headers(vec![("Content-Type".to_string(), "text/plain".to_string())])
+            .with_body(b"Bad Request: MCP-Protocol-Version does not match the negotiated version")
+            .build()
+    );
+}
+
+#[test]
+fn test_structured_output() {
+    #[derive(Serialize, JsonSchema)]
+    struct Magic {
+        sum: f64,
+    }
+
+    struct Structured;
+    impl Handler for Structured {
+        async fn list_tools(
+            &self,
+            _: Context,
+            _: Option<PaginatedRequestParam>,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult {
+                next_cursor: None,
+                tools: vec![
+                    Tool::new("magic", "Magic sum", schema_for_type::<MagicSumRequest>())
+                        .with_output_schema::<Magic>(),
+                ],
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _: Context,
+            _: CallToolRequestParam,
+        ) -> Result<CallToolResult, Error> {
+            structured_result(&Magic { sum: 7.0 })
+        }
+    }
+
+    let post = |version: Option<&str>, body: &'static [u8]| {
+        block_on(
+            Structured {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_headers(
+                        version
+                            .map(|v| ("MCP-Protocol-Version".to_string(), v.to_string()))
+                            .into_iter()
+                            .collect(),
+                    )
+                    .with_body(body)
+                    .build(),
+                |_| true,
+            ),
+        )
+        .body()
+        .to_vec()
+    };
+    let call = br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"magic","arguments":{"a":1,"b":2}}}"#;
+    let list = br#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+
+    assert_eq!(
+        post(Some("2025-06-18"), call),
+        br#"{"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"{\"sum\":7.0}"}],"structuredContent":{"sum":7.0},"isError":false}}"#
+    );
+    assert_eq!(
+        post(None, call),
+        br#"{"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"{\"sum\":7.0}"}],"isError":false}}"#
+    );
+
+    let tools: Value = serde_json::from_slice(&post(Some("2025-06-18"), list)).unwrap();
+    assert_eq!(
+        tools["result"]["tools"][0]["outputSchema"],
+        Value::Object(schema_for_type::<Magic>())
+    );
+    assert_eq!(
+        tools["result"]["tools"][0]["inputSchema"],
+        Value::Object(schema_for_type::<MagicSumRequest>())
+    );
+
+    let tools: Value = serde_json::from_slice(&post(Some("2025-03-26"), list)).unwrap();
+    assert_eq!(tools["result"]["tools"][0].get("outputSchema"), None);
+    assert_eq!(
+        tools["result"]["tools"][0]["inputSchema"],
+        Value::Object(schema_for_type::<MagicSumRequest>())
+    );
+}
+
+#[test]
+fn test_tool_router() {
+    struct Calculator;
+
+    #[tool_router]
+    impl Calculator {
+        /// Calculate a magic sum between two numbers
+        #[tool]
+        async fn magic_sum(&self, args: MagicSumRequest) -> Result<CallToolResult, Error> {
+            Ok(CallToolResult::success(
+                Content::text(format!("{:.2}", args.a + 3.0 * args.b)).into_contents(),
+            ))
+        }
+
+        #[tool(
+            name = "whoami",
+            description = "Return the caller",
+            title = "Who am I",
+            read_only,
+            open_world = false,
+            query
+        )]
+        async fn caller(&self, context: Context) -> Result<CallToolResult, Error> {
+            Ok(CallToolResult::success(
+                Content::text(context.subject.unwrap_or_default()).into_contents(),
+            ))
+        }
+
+        #[allow(dead_code)]
+        fn helper(&self) {}
+    }
+
+    #[tool_handler]
+    impl Handler for Calculator {}
+
+    assert_eq!(
+        Calculator::tools(),
+        vec![
+            Tool::new(
+                "magic_sum",
+                "Calculate a magic sum between two numbers",
+                schema_for_type::<MagicSumRequest>(),
+            ),
+            Tool::new(
+                "whoami",
+                "Return the caller",
+                schema_for_type::<EmptyObject>()
+            )
+            .with_title("Who am I")
+            .read_only(true)
+            .open_world(false),
+        ]
+    );
+
+    let call = |name: &str, arguments: Value| {
+        let mut context = Context::default();
+        context.subject = Some("alice".to_string());
+        block_on(Calculator {}.call_tool(
+            context,
+            CallToolRequestParam {
+                name: name.to_string().into(),
+                arguments: arguments.as_object().cloned(),
+            },
+        ))
+    };
+
+    assert_eq!(
+        call("magic_sum", json!({"a": 1, "b": 2})),
+        Ok(CallToolResult::success(
+            Content::text("7.00").into_contents()
+        ))
+    );
+    assert_eq!(
+        call("whoami", Value::Null),
+        Ok(CallToolResult::success(
+            Content::text("alice").into_contents()
+        ))
+    );
+    assert_eq!(
+        call("magic_sum", json!({"a": 1})),
+        Err(Error::invalid_params(
+            "invalid arguments to tool magic_sum: missing field `b`",
+            None
+        ))
+    );
+    assert_eq!(
+        call("unknown", Value::Null),
+        Err(Error::invalid_params("not found tool unknown", None))
+    );
+    assert_eq!(
+        block_on(Calculator {}.list_tools(Context::default(), None)).map(|r| r.tools),
+        Ok(Calculator::tools())
+    );
+    assert!(Calculator {}.query_tool("whoami"));
+    assert!(!Calculator {}.query_tool("magic_sum"));
+}
+
+#[test]
+fn test_tool_error() {
+    struct Divider;
+
+    #[tool_router]
+    impl Divider {
+        /// Divide a by b
+        #[tool]
+        async fn divide(&self, args: MagicSumRequest) -> Result<CallToolResult, ToolError> {
+            if args.b == 0.0 {
+                return Err(ToolError::new("division by zero"));
+            }
+            Ok(CallToolResult::success(
+                Content::text(format!("{:.2}", args.a / args.b)).into_contents(),
+            ))
+        }
+    }
+
+    #[tool_handler]
+    impl Handler for Divider {}
+
+    let call = |name: &str, arguments: Value| {
+        block_on(Divider {}.call_tool(
+            Context::default(),
+            CallToolRequestParam {
+                name: name.to_string().into(),
+                arguments: arguments.as_object().cloned(),
+            },
+        ))
+    };
+
+    assert_eq!(
+        call("divide", json!({"a": 1, "b": 4})),
+        Ok(CallToolResult::success(
+            Content::text("0.25").into_contents()
+        ))
+    );
+    assert_eq!(
+        call("divide", json!({"a": 1, "b": 0})),
+        Ok(CallToolResult::error(
+            Content::text("division by zero").into_contents()
+        ))
+    );
+    assert_eq!(
+        call("divide", json!({"a": 1})),
+        Err(Error::invalid_params(
+            "invalid arguments to tool divide: missing field `b`",
+            None
+        ))
+    );
+}
+
+#[test]
+fn test_argument_validation() {
+    struct Validated;
+    impl Handler for Validated {
+        fn validate_arguments(&self) -> bool {
+            true
+        }
+
+        async fn list_tools(
+            &self,
+            _: Context,
+            _: Option<PaginatedRequestParam>,
+        ) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult {
+                next_cursor: None,
+                tools: vec![Tool::new(
+                    "magic",
+                    "Magic sum",
+                    schema_for_type::<MagicSumRequest>(),
+                )],
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _: Context,
+            request: CallToolRequestParam,
+        ) -> Result<CallToolResult, Error> {
+            Ok(CallToolResult::success(
+                Content::text(request.name).into_contents(),
+            ))
+        }
+    }
+
+    let post = |body: &'static [u8]| {
+        block_on(
+            Validated {}.handle(
+                &HttpRequest::builder()
+                    .with_method(Method::POST)
+                    .with_url("/mcp")
+                    .with_body(body)
+                    .build(),
+                |_| true,
+            ),
+        )
+        .body()
+        .to_vec()
+    };
+    let post = |body| serde_json::from_slice::<Value>(&post(body)).unwrap();
+
+    assert_eq!(
+        post(
+            br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"magic","arguments":{"a":1,"b":2}}}"#
+        ),
+        json!({"jsonrpc": "2.0", "id": 1, "result": {"content": [{"type": "text", "text": "magic"}], "isError": false}})
+    );
+    assert_eq!(
+        post(
+            br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"magic","arguments":{"a":"1"}}}"#
+        ),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {
+                "code": -32602,
+                "message": "invalid arguments to tool magic: /a must be of type number; /b is required",
+                "data": {"errors": [
+                    {"pointer": "/a", "message": "must be of type number"},
+                    {"pointer": "/b", "message": "is required"}
+                ]}
+            }
+        })
+    );
+    assert_eq!(
+        post(br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"other"}}"#),
+        json!({"jsonrpc": "2.0", "id": 1, "result": {"content": [{"type": "text", "text": "other"}], "isError": false}})
+    );
+}